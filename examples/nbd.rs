@@ -0,0 +1,27 @@
+//! Attach a disk image served over NBD and list its filesystems
+//!
+//! Serve an image on a Unix socket first, e.g.
+//!
+//! ```sh
+//! qemu-nbd --socket=/tmp/nbd.sock --format=qcow2 --read-only disk.qcow2
+//! cargo run --example nbd -- /tmp/nbd.sock
+//! ```
+use hvk::guestfs::{DriveSource, Server};
+use hvk::prelude::*;
+
+fn main() -> hvk::Result<()> {
+    let socket = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "/tmp/nbd.sock".into());
+
+    let mut fs = GuestFileSystem::new()?;
+    // qemu-nbd decodes the image, so the export itself is always raw
+    let source = DriveSource::nbd(Server::unix(socket));
+    fs.add_drive(&DriveOptions::new(source, DriveFormat::Raw).readonly(true))?;
+    let fs = fs.launch()?;
+
+    for (filesystem, fstype) in fs.list_filesystems()? {
+        println!("{filesystem}: {fstype}");
+    }
+    Ok(())
+}
@@ -1,17 +1,129 @@
+use std::{ffi::OsString, fmt, io, path::Path};
+
 // error types powered by thiserror
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
 #[must_use]
 pub enum Error {
-    // we gotta map from c int
-    #[error("libguestfs error: {0}")]
-    GuestFsError(String),
+    /// A libguestfs call failed
+    #[error(transparent)]
+    GuestFs(GuestFsError),
+    /// A libguestfs call was cancelled through a [`CancelHandle`](crate::guestfs::CancelHandle)
+    #[error("libguestfs call {}() was cancelled", .0.api())]
+    Cancelled(#[source] GuestFsError),
+    /// A libguestfs call did not finish before the deadline of
+    /// [`GuestFs::with_timeout`](crate::guestfs::GuestFs::with_timeout)
+    #[error("libguestfs call {}() timed out", .0.api())]
+    TimedOut(#[source] GuestFsError),
+    #[error("Failed to create libguestfs handle: {0}")]
+    Create(io::Error),
+    #[error("Invalid drive: {0}")]
+    InvalidDrive(String),
+    /// A device name could not be parsed, see [`Device`](crate::guestfs::Device)
+    #[error("Invalid device: {0}")]
+    InvalidDevice(String),
     #[error("I/O Error: {0}")]
-    IoError(#[from] std::io::Error),
+    Io(#[from] io::Error),
 
     #[error("Invalid UTF-8: {0}")]
-    Utf8Error(#[from] std::str::Utf8Error),
+    Utf8(#[from] std::str::Utf8Error),
 
     #[error("Invalid CString: {0}")]
-    NulError(#[from] std::ffi::NulError),
+    Nul(#[from] std::ffi::NulError),
+}
+
+impl Error {
+    /// The category of the error, like [`io::Error::kind`]
+    ///
+    /// Errors reported by libguestfs are categorized by their `errno`, so this can be used to
+    /// tell e.g. a missing file ([`io::ErrorKind::NotFound`]) apart from other failures.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::GuestFs(error) => error.kind(),
+            // not `Interrupted`, which callers of `Read` and `Write` retry
+            Error::Cancelled(_) => io::ErrorKind::Other,
+            Error::TimedOut(_) => io::ErrorKind::TimedOut,
+            Error::Create(error) | Error::Io(error) => error.kind(),
+            Error::InvalidDrive(_) | Error::InvalidDevice(_) | Error::Nul(_) => {
+                io::ErrorKind::InvalidInput
+            }
+            Error::Utf8(_) => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            error => io::Error::new(error.kind(), error),
+        }
+    }
 }
+
+/// An error reported by a libguestfs call
+#[derive(Debug, Clone)]
+pub struct GuestFsError {
+    api: &'static str,
+    args: Vec<OsString>,
+    message: String,
+    errno: Option<i32>,
+}
+
+impl GuestFsError {
+    pub(crate) fn new(api: &'static str, args: Vec<OsString>, message: String, errno: i32) -> Self {
+        Self {
+            api,
+            args,
+            message,
+            errno: (errno != 0).then_some(errno),
+        }
+    }
+
+    /// The name of the libguestfs call that failed, without the `guestfs_` prefix, e.g. `mount`
+    pub fn api(&self) -> &'static str {
+        self.api
+    }
+
+    /// The guest paths and devices the call was made with
+    pub fn args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    /// The error message from libguestfs
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The `errno` of the failure, if libguestfs reported one
+    ///
+    /// libguestfs translates errors of the appliance to the `errno` values of the host.
+    pub fn errno(&self) -> Option<i32> {
+        self.errno
+    }
+
+    /// The category of the error, decoded from the `errno`
+    ///
+    /// This is [`io::ErrorKind::Other`] if there is no `errno`.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self.errno {
+            Some(errno) => io::Error::from_raw_os_error(errno).kind(),
+            None => io::ErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for GuestFsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "libguestfs error in {}(", self.api)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{:?}", Path::new(arg))?;
+        }
+        write!(f, "): {}", self.message)
+    }
+}
+
+impl std::error::Error for GuestFsError {}
@@ -0,0 +1,422 @@
+use super::{io_error, GuestFileSystem, Metadata};
+use crate::Result;
+use std::{
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+// largest read or write sent to the appliance at once, well below its message size limit
+const IO_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A representation of a file inside of a `[GuestFileSystem]`
+///
+/// The file implements [`Read`], [`Write`] and [`Seek`] on top of `guestfs_pread` and
+/// `guestfs_pwrite`, so it can be used anywhere a regular file handle can. There is no
+/// buffering, every call is a round-trip to the appliance of at most 1 MiB, so wrap it in a
+/// [`std::io::BufReader`] or [`std::io::BufWriter`] for many small reads or writes.
+///
+/// The file borrows the filesystem it belongs to, so it cannot outlive it:
+///
+/// ```compile_fail
+/// # use hvk::fs::{GuestFile, GuestFileSystem};
+/// # fn f(fs: GuestFileSystem) {
+/// let file = GuestFile::create(&fs, "/etc/motd").unwrap();
+/// drop(fs);
+/// file.cat().unwrap();
+/// # }
+/// ```
+///
+/// and the filesystem cannot be unmounted while the file is alive:
+///
+/// ```compile_fail
+/// # use hvk::fs::{GuestFile, GuestFileSystem};
+/// # fn f(mut fs: GuestFileSystem) {
+/// let file = GuestFile::create(&fs, "/etc/motd").unwrap();
+/// fs.umount("/").unwrap();
+/// file.cat().unwrap();
+/// # }
+/// ```
+pub struct GuestFile<'fs> {
+    fs: &'fs GuestFileSystem<'fs>,
+    path: PathBuf,
+    pos: u64,
+    access: Access,
+}
+
+/// What a [`GuestFile`] was opened for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Access {
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl Access {
+    fn writable(self) -> bool {
+        self.write || self.append
+    }
+
+    fn check_read(self) -> Result<()> {
+        if !self.read {
+            return Err(not_opened_for("reading"));
+        }
+        Ok(())
+    }
+
+    fn check_write(self) -> Result<()> {
+        if !self.writable() {
+            return Err(not_opened_for("writing"));
+        }
+        Ok(())
+    }
+}
+
+/// The position `delta` bytes away from `base`, failing like std on negative positions
+fn offset_position(base: u64, delta: i64) -> io::Result<u64> {
+    base.checked_add_signed(delta).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
+
+impl<'fs> GuestFile<'fs> {
+    /// Open a file in read-only mode, see [`std::fs::File::open`]
+    pub fn open(fs: &'fs GuestFileSystem<'fs>, path: impl AsRef<Path>) -> Result<Self> {
+        OpenOptions::new().read(true).open(fs, path)
+    }
+
+    /// Open a file in write-only mode, creating it if it does not exist and truncating it if it does,
+    /// see [`std::fs::File::create`]
+    pub fn create(fs: &'fs GuestFileSystem<'fs>, path: impl AsRef<Path>) -> Result<Self> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(fs, path)
+    }
+
+    /// The path of the file inside the guest
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The filesystem the file belongs to
+    pub fn filesystem(&self) -> &'fs GuestFileSystem<'fs> {
+        self.fs
+    }
+
+    /// Query the metadata of the file, see [`std::fs::File::metadata`]
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.fs.metadata(&self.path)
+    }
+
+    /// Truncate or extend the file to `size` bytes, see [`std::fs::File::set_len`]
+    ///
+    /// The cursor position is not changed.
+    pub fn set_len(&self, size: u64) -> Result<()> {
+        self.access.check_write()?;
+        let size = i64::try_from(size)
+            .map_err(|_| io_error(io::ErrorKind::InvalidInput, "file size too large"))?;
+        self.fs.inner().truncate_size(&self.path, size)
+    }
+
+    /// Flush all pending writes to the disks, see [`std::fs::File::sync_all`]
+    ///
+    /// libguestfs can only sync whole disks, so this syncs every filesystem of the guest.
+    pub fn sync_all(&self) -> Result<()> {
+        self.fs.inner().sync()
+    }
+
+    pub fn download(&self, dest: &Path) -> Result<()> {
+        self.fs.inner().download(&self.path, dest)
+    }
+
+    /// Stream the whole file into `writer`, returning the number of bytes copied
+    ///
+    /// This is much faster than [`io::copy`] from the file, since the data is transferred
+    /// in a single call instead of one round-trip per chunk. The cursor position is not used.
+    pub fn download_to(&self, writer: impl Write + Send) -> Result<u64> {
+        self.fs.inner().download_to(&self.path, writer)
+    }
+
+    pub fn cat(&self) -> Result<Box<[u8]>> {
+        Ok(self.fs.inner().cat(&self.path)?.as_bytes().into())
+    }
+
+    fn offset(&self) -> io::Result<i64> {
+        i64::try_from(self.pos)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file offset too large"))
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.fs.inner().filesize(&self.path)? as u64)
+    }
+}
+
+fn not_opened_for(mode: &str) -> crate::error::Error {
+    io_error(
+        io::ErrorKind::PermissionDenied,
+        &format!("file was not opened for {mode}"),
+    )
+}
+
+impl Read for GuestFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.access.check_read()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let count = buf.len().min(IO_CHUNK_SIZE);
+        let data = self
+            .fs
+            .inner()
+            .pread(&self.path, count as i32, self.offset()?)?;
+        // never trust the appliance to return no more than requested
+        let read = data.len().min(count);
+        buf[..read].copy_from_slice(&data[..read]);
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Write for GuestFile<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.access.check_write()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.access.append {
+            self.pos = self.len()?;
+        }
+
+        let chunk = &buf[..buf.len().min(IO_CHUNK_SIZE)];
+        let written = self.fs.inner().pwrite(&self.path, chunk, self.offset()?)?;
+        self.pos += written as u64;
+        Ok(written)
+    }
+
+    /// Writes are not buffered, so this does nothing. See [`GuestFile::sync_all`].
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for GuestFile<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::Current(delta) => (self.pos, delta),
+            SeekFrom::End(delta) => (self.len()?, delta),
+        };
+        self.pos = offset_position(base, delta)?;
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+/// Options to configure how a [`GuestFile`] is opened, see [`std::fs::OpenOptions`]
+///
+/// ```ignore
+/// use hvk::fs::{GuestFile, OpenOptions};
+/// use std::io::Write;
+///
+/// let mut log = OpenOptions::new()
+///     .append(true)
+///     .create(true)
+///     .open(&fs, "/var/log/hvk.log")?;
+/// writeln!(log, "hello from the host")?;
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl OpenOptions {
+    /// Create a blank set of options, with every option disabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the file for reading
+    pub fn read(mut self, read: bool) -> Self {
+        self.read = read;
+        self
+    }
+
+    /// Open the file for writing
+    pub fn write(mut self, write: bool) -> Self {
+        self.write = write;
+        self
+    }
+
+    /// Open the file for writing, always at the end of the file
+    ///
+    /// The end of the file is looked up before every write.
+    pub fn append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Truncate the file to 0 bytes if it already exists, requires `write`
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Create the file if it does not exist, requires `write` or `append`
+    pub fn create(mut self, create: bool) -> Self {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, failing if it already exists, requires `write` or `append`
+    ///
+    /// Unlike in std, the check and the creation are two separate calls to the appliance,
+    /// so this is only atomic if nothing else modifies the guest at the same time.
+    pub fn create_new(mut self, create_new: bool) -> Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Check the combination of options, returning what the file is opened for
+    fn access(&self) -> Result<Access> {
+        let writable = self.write || self.append;
+        // the same combinations std rejects
+        if !self.read && !writable {
+            return Err(io_error(
+                io::ErrorKind::InvalidInput,
+                "file must be opened for reading or writing",
+            ));
+        }
+        if !writable && (self.truncate || self.create || self.create_new) {
+            return Err(io_error(
+                io::ErrorKind::InvalidInput,
+                "creating or truncating a file requires write access",
+            ));
+        }
+        if self.truncate && self.append && !self.create_new {
+            return Err(io_error(
+                io::ErrorKind::InvalidInput,
+                "a file cannot be truncated and opened for appending",
+            ));
+        }
+        Ok(Access {
+            read: self.read,
+            write: self.write,
+            append: self.append,
+        })
+    }
+
+    /// Open the file at `path` with these options
+    pub fn open<'fs>(
+        &self,
+        fs: &'fs GuestFileSystem<'fs>,
+        path: impl AsRef<Path>,
+    ) -> Result<GuestFile<'fs>> {
+        let path = path.as_ref().to_path_buf();
+        let access = self.access()?;
+        let writable = access.writable();
+
+        let inner = fs.inner();
+        let exists = {
+            let _quiet = fs.silence_errors();
+            inner.exists(&path)?
+        };
+        if !exists {
+            if !self.create && !self.create_new {
+                return Err(io_error(io::ErrorKind::NotFound, "file does not exist"));
+            }
+            inner.write(&path, &[])?;
+        } else if self.create_new {
+            return Err(io_error(
+                io::ErrorKind::AlreadyExists,
+                "file already exists",
+            ));
+        } else {
+            let metadata = fs.metadata(&path)?;
+            if writable && metadata.is_dir() {
+                return Err(io_error(io::ErrorKind::IsADirectory, "file is a directory"));
+            }
+            if self.truncate && metadata.len() > 0 {
+                inner.truncate_size(&path, 0)?;
+            }
+        }
+
+        Ok(GuestFile {
+            fs,
+            path,
+            pos: 0,
+            access,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_options_are_rejected_like_std() {
+        for options in [
+            OpenOptions::new(),
+            OpenOptions::new().read(true).truncate(true),
+            OpenOptions::new().read(true).create(true),
+            OpenOptions::new().read(true).create_new(true),
+            OpenOptions::new().append(true).truncate(true),
+        ] {
+            let error = options.access().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{options:?}");
+        }
+    }
+
+    #[test]
+    fn access_follows_the_options() {
+        let access = OpenOptions::new().read(true).access().unwrap();
+        assert!(access.check_read().is_ok());
+        let error = access.check_write().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+
+        for options in [
+            OpenOptions::new().write(true).truncate(true),
+            OpenOptions::new().append(true).create(true),
+            OpenOptions::new()
+                .append(true)
+                .truncate(true)
+                .create_new(true),
+        ] {
+            let access = options.access().unwrap();
+            assert!(access.check_write().is_ok(), "{options:?}");
+            let error = access.check_read().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        }
+
+        let access = OpenOptions::new().read(true).append(true).access().unwrap();
+        assert!(access.append && access.check_read().is_ok() && access.check_write().is_ok());
+    }
+
+    #[test]
+    fn seek_offsets() {
+        assert_eq!(offset_position(10, 5).unwrap(), 15);
+        assert_eq!(offset_position(10, -10).unwrap(), 0);
+        assert_eq!(offset_position(0, i64::MAX).unwrap(), i64::MAX as u64);
+        for (base, delta) in [(0, -1), (10, -11), (u64::MAX, 1)] {
+            let error = offset_position(base, delta).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
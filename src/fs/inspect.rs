@@ -0,0 +1,381 @@
+//! Typed results of guest OS inspection
+use super::{GuestFileSystem, MountMode};
+use crate::{
+    guestfs::{types::Application2, Device, GuestFs, Launched, Mountable},
+    Result,
+};
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+/// Define an enum of the strings returned by an inspection function, with catch-all variants
+/// for `"unknown"` and for values added by newer versions of libguestfs
+macro_rules! inspection_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// Could not be determined
+            Unknown,
+            /// Any other value reported by libguestfs
+            Other(String),
+        }
+
+        impl $name {
+            /// The string used by libguestfs for this value
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $value,)*
+                    Self::Unknown => "unknown",
+                    Self::Other(value) => value,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => Self::$variant,)*
+                    "unknown" => Self::Unknown,
+                    _ => Self::Other(value.to_owned()),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+inspection_enum! {
+    /// The type of an operating system, see `guestfs_inspect_get_type`
+    ///
+    /// ```
+    /// use hvk::fs::OsType;
+    ///
+    /// assert_eq!(OsType::from("linux"), OsType::Linux);
+    /// assert_eq!(OsType::from("plan9"), OsType::Other("plan9".into()));
+    /// assert_eq!(OsType::Windows.to_string(), "windows");
+    /// ```
+    pub enum OsType {
+        /// Linux
+        Linux => "linux",
+        /// Windows
+        Windows => "windows",
+        /// FreeBSD
+        FreeBsd => "freebsd",
+        /// NetBSD
+        NetBsd => "netbsd",
+        /// OpenBSD
+        OpenBsd => "openbsd",
+        /// GNU Hurd
+        Hurd => "hurd",
+        /// DOS
+        Dos => "dos",
+        /// MINIX
+        Minix => "minix",
+    }
+}
+
+inspection_enum! {
+    /// The distribution of an operating system, see `guestfs_inspect_get_distro`
+    pub enum Distro {
+        /// Alpine Linux
+        AlpineLinux => "alpinelinux",
+        /// ALT Linux
+        AltLinux => "altlinux",
+        /// Arch Linux
+        ArchLinux => "archlinux",
+        /// Buildroot
+        Buildroot => "buildroot",
+        /// CentOS
+        CentOs => "centos",
+        /// CirrOS
+        CirrOs => "cirros",
+        /// CoreOS
+        CoreOs => "coreos",
+        /// Debian
+        Debian => "debian",
+        /// Fedora
+        Fedora => "fedora",
+        /// FreeBSD
+        FreeBsd => "freebsd",
+        /// FreeDOS
+        FreeDos => "freedos",
+        /// Frugalware
+        Frugalware => "frugalware",
+        /// Gentoo
+        Gentoo => "gentoo",
+        /// Kali Linux
+        KaliLinux => "kalilinux",
+        /// Linux Mint
+        LinuxMint => "linuxmint",
+        /// Mageia
+        Mageia => "mageia",
+        /// Mandriva
+        Mandriva => "mandriva",
+        /// MeeGo
+        MeeGo => "meego",
+        /// Microsoft Windows
+        Windows => "windows",
+        /// NetBSD
+        NetBsd => "netbsd",
+        /// Oracle Linux
+        OracleLinux => "oraclelinux",
+        /// openSUSE
+        OpenSuse => "opensuse",
+        /// OpenBSD
+        OpenBsd => "openbsd",
+        /// Red Hat Enterprise Linux
+        Rhel => "rhel",
+        /// Rocky Linux
+        Rocky => "rocky",
+        /// Scientific Linux
+        ScientificLinux => "scientificlinux",
+        /// Slackware
+        Slackware => "slackware",
+        /// SUSE Linux Enterprise
+        Sles => "sles",
+        /// TTY Linux
+        TtyLinux => "ttylinux",
+        /// Ubuntu
+        Ubuntu => "ubuntu",
+        /// Void Linux
+        VoidLinux => "voidlinux",
+    }
+}
+
+inspection_enum! {
+    /// The package format of an operating system, see `guestfs_inspect_get_package_format`
+    pub enum PackageFormat {
+        /// RPM packages
+        Rpm => "rpm",
+        /// Debian packages
+        Deb => "deb",
+        /// Arch Linux packages
+        Pacman => "pacman",
+        /// Gentoo ebuilds
+        Ebuild => "ebuild",
+        /// Pardus packages
+        Pisi => "pisi",
+        /// Alpine packages
+        Apk => "apk",
+        /// Void Linux packages
+        Xbps => "xbps",
+        /// BSD packages
+        Pkgsrc => "pkgsrc",
+    }
+}
+
+inspection_enum! {
+    /// The package manager of an operating system, see `guestfs_inspect_get_package_management`
+    pub enum PackageManager {
+        /// apt
+        Apt => "apt",
+        /// dnf
+        Dnf => "dnf",
+        /// yum
+        Yum => "yum",
+        /// up2date
+        Up2date => "up2date",
+        /// urpmi
+        Urpmi => "urpmi",
+        /// zypper
+        Zypper => "zypper",
+        /// pacman
+        Pacman => "pacman",
+        /// portage
+        Portage => "portage",
+        /// pisi
+        Pisi => "pisi",
+        /// apk
+        Apk => "apk",
+        /// xbps
+        Xbps => "xbps",
+    }
+}
+
+/// An application installed in an operating system, see [`GuestFileSystem::list_applications`]
+///
+/// On Linux, this is a package of the package manager.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Application {
+    /// The package name, e.g. `bash`
+    pub name: String,
+    /// The package epoch, `0` if there is none
+    pub epoch: i32,
+    /// The package version, e.g. `5.2.26`
+    pub version: String,
+    /// The package release, e.g. `3.fc40`
+    pub release: Option<String>,
+    /// The architecture the package was built for, e.g. `x86_64` or `noarch`
+    pub arch: Option<String>,
+    /// The installation directory, only known on Windows
+    pub install_path: Option<String>,
+    /// The publisher, or the vendor of the package
+    pub publisher: Option<String>,
+    /// The home page of the application
+    pub url: Option<String>,
+    /// The source package the package was built from
+    pub source_package: Option<String>,
+    /// A one-line description
+    pub summary: Option<String>,
+    /// A longer description
+    pub description: Option<String>,
+}
+
+impl Application {
+    /// The full version, as `[epoch:]version[-release]` like RPM formats it
+    ///
+    /// ```ignore
+    /// assert_eq!(bash.evr(), "5.2.26-3.fc40");
+    /// ```
+    pub fn evr(&self) -> String {
+        let mut evr = match self.epoch {
+            0 => String::new(),
+            epoch => format!("{epoch}:"),
+        };
+        evr.push_str(&self.version);
+        if let Some(release) = &self.release {
+            evr.push('-');
+            evr.push_str(release);
+        }
+        evr
+    }
+}
+
+impl From<Application2<'_>> for Application {
+    fn from(app: Application2<'_>) -> Self {
+        // libguestfs leaves the fields it does not know empty
+        let known =
+            |value: std::borrow::Cow<'_, str>| (!value.is_empty()).then(|| value.into_owned());
+        Self {
+            name: app.name().into_owned(),
+            epoch: app.epoch(),
+            version: app.version().into_owned(),
+            release: known(app.release()),
+            arch: known(app.arch()),
+            install_path: known(app.install_path()),
+            publisher: known(app.publisher()),
+            url: known(app.url()),
+            source_package: known(app.source_package()),
+            summary: known(app.summary()),
+            description: known(app.description()),
+        }
+    }
+}
+
+/// An operating system found on the disks, see [`GuestFileSystem::inspect`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OsInfo {
+    /// The root device of the operating system, which identifies it
+    pub root: Mountable,
+    /// The type of the operating system
+    pub os_type: OsType,
+    /// The distribution of the operating system
+    pub distro: Distro,
+    /// The major version, `0` if unknown
+    pub major_version: i32,
+    /// The minor version, `0` if unknown
+    pub minor_version: i32,
+    /// The product name, e.g. `Fedora release 39 (Thirty Nine)`
+    pub product_name: Option<String>,
+    /// The product variant, e.g. `Server` or `Client` on Windows
+    pub product_variant: Option<String>,
+    /// The architecture, e.g. `x86_64`
+    pub arch: Option<String>,
+    /// The configured hostname
+    pub hostname: Option<String>,
+    /// The package format
+    pub package_format: PackageFormat,
+    /// The package manager
+    pub package_manager: PackageManager,
+    /// The libosinfo short ID, e.g. `fedora39`
+    pub osinfo: Option<String>,
+    /// The Windows drive letters, mapped to their devices
+    pub drive_mappings: BTreeMap<String, Device>,
+    /// The mountpoints, e.g. from `/etc/fstab`, mapped to their devices
+    ///
+    /// Parents sort before their children, so iterating mounts `/` before `/usr`.
+    pub mountpoints: BTreeMap<PathBuf, Mountable>,
+}
+
+impl OsInfo {
+    fn inspect(fs: &GuestFs<'_, Launched>, root: Mountable) -> Result<Self> {
+        // libguestfs reports what it could not determine as "unknown"
+        let known = |value: String| (value != "unknown").then_some(value);
+        Ok(Self {
+            os_type: fs.inspect_get_type(&root)?.as_str().into(),
+            distro: fs.inspect_get_distro(&root)?.as_str().into(),
+            major_version: fs.inspect_get_major_version(&root)?,
+            minor_version: fs.inspect_get_minor_version(&root)?,
+            product_name: known(fs.inspect_get_product_name(&root)?),
+            product_variant: known(fs.inspect_get_product_variant(&root)?),
+            arch: known(fs.inspect_get_arch(&root)?),
+            hostname: known(fs.inspect_get_hostname(&root)?),
+            package_format: fs.inspect_get_package_format(&root)?.as_str().into(),
+            package_manager: fs.inspect_get_package_management(&root)?.as_str().into(),
+            osinfo: known(fs.inspect_get_osinfo(&root)?),
+            drive_mappings: fs.inspect_get_drive_mappings(&root)?,
+            mountpoints: fs.inspect_get_mountpoints(&root)?,
+            root,
+        })
+    }
+}
+
+impl GuestFileSystem<'_, Launched> {
+    /// Inspect the disks for operating systems
+    ///
+    /// Returns one [`OsInfo`] per operating system found, which is empty if there are none.
+    /// libguestfs mounts filesystems while inspecting, so this is best called before mounting
+    /// anything; nothing is left mounted afterwards.
+    ///
+    /// ```ignore
+    /// for os in fs.inspect()? {
+    ///     println!("{}: {} {}.{}", os.root, os.distro, os.major_version, os.minor_version);
+    /// }
+    /// ```
+    pub fn inspect(&mut self) -> Result<Vec<OsInfo>> {
+        let roots = self.inner.inspect_os()?;
+        roots
+            .into_vec()
+            .into_iter()
+            .map(|root| OsInfo::inspect(&self.inner, root))
+            .collect()
+    }
+
+    /// List the applications installed in an operating system found by [`inspect`](Self::inspect)
+    ///
+    /// The filesystems of the operating system are mounted read-only with
+    /// [`mount_os`](Self::mount_os) while the package database is read, so nothing may be
+    /// mounted when this is called. Reading the database of a large system takes a while.
+    ///
+    /// ```ignore
+    /// let os = fs.inspect()?.remove(0);
+    /// for app in fs.list_applications(&os)? {
+    ///     println!("{} {}", app.name, app.evr());
+    /// }
+    /// ```
+    #[doc(alias = "guestfs_inspect_list_applications2")]
+    pub fn list_applications(&mut self, os: &OsInfo) -> Result<Vec<Application>> {
+        let mounted = self.mount_os(os, MountMode::ReadOnly)?;
+        let applications = mounted
+            .inner
+            .inspect_list_applications2(&os.root)?
+            .iter()
+            .map(Application::from)
+            .collect();
+        mounted.unmount()?;
+        Ok(applications)
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{error::Error, guestfs::types::StatNs, Result};
+use std::{
+    io,
+    time::{Duration, SystemTime},
+};
+
+// file type bits of st_mode, these are the same on every Linux architecture
+const S_IFMT: u32 = 0o170000;
+const S_IFSOCK: u32 = 0o140000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFREG: u32 = 0o100000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFIFO: u32 = 0o010000;
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_ISVTX: u32 = 0o1000;
+
+/// A timestamp of a file, split like in `struct timespec`
+#[derive(Debug, Clone, Copy)]
+struct Timespec {
+    sec: i64,
+    nsec: i64,
+}
+
+impl Timespec {
+    fn to_system_time(self) -> SystemTime {
+        let nsec = Duration::from_nanos(self.nsec.clamp(0, 999_999_999) as u64);
+        if self.sec >= 0 {
+            SystemTime::UNIX_EPOCH + Duration::from_secs(self.sec as u64) + nsec
+        } else {
+            SystemTime::UNIX_EPOCH - Duration::from_secs(self.sec.unsigned_abs()) + nsec
+        }
+    }
+}
+
+/// Metadata information about a file inside the guest
+///
+/// This is the equivalent of [`std::fs::Metadata`], returned by
+/// [`GuestFileSystem::metadata`](super::GuestFileSystem::metadata) and
+/// [`GuestFileSystem::symlink_metadata`](super::GuestFileSystem::symlink_metadata).
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    dev: u64,
+    ino: u64,
+    mode: u32,
+    nlink: u64,
+    uid: u32,
+    gid: u32,
+    rdev: u64,
+    size: u64,
+    blksize: u64,
+    blocks: u64,
+    atime: Timespec,
+    mtime: Timespec,
+    ctime: Timespec,
+}
+
+impl Metadata {
+    pub(crate) fn from_stat(stat: &StatNs) -> Self {
+        Self::from_raw(stat.get())
+    }
+
+    pub(crate) fn from_raw(stat: &libguestfs_sys::guestfs_statns) -> Self {
+        Self {
+            dev: stat.st_dev as u64,
+            ino: stat.st_ino as u64,
+            mode: stat.st_mode as u32,
+            nlink: stat.st_nlink as u64,
+            uid: stat.st_uid as u32,
+            gid: stat.st_gid as u32,
+            rdev: stat.st_rdev as u64,
+            size: stat.st_size as u64,
+            blksize: stat.st_blksize as u64,
+            blocks: stat.st_blocks as u64,
+            atime: Timespec {
+                sec: stat.st_atime_sec,
+                nsec: stat.st_atime_nsec,
+            },
+            mtime: Timespec {
+                sec: stat.st_mtime_sec,
+                nsec: stat.st_mtime_nsec,
+            },
+            ctime: Timespec {
+                sec: stat.st_ctime_sec,
+                nsec: stat.st_ctime_nsec,
+            },
+        }
+    }
+
+    /// The type of the file
+    pub fn file_type(&self) -> FileType {
+        FileType(self.mode & S_IFMT)
+    }
+
+    /// Whether this is a directory
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Whether this is a regular file
+    pub fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    /// Whether this is a symbolic link, only possible with `symlink_metadata`
+    pub fn is_symlink(&self) -> bool {
+        self.file_type().is_symlink()
+    }
+
+    /// The size of the file in bytes
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    /// The permission bits of the file
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.mode)
+    }
+
+    /// The full `st_mode`, including the file type bits
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// ID of the device containing the file
+    pub fn dev(&self) -> u64 {
+        self.dev
+    }
+
+    /// Inode number
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// Number of hard links
+    pub fn nlink(&self) -> u64 {
+        self.nlink
+    }
+
+    /// User ID of the owner, as seen by the guest
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Group ID of the owner, as seen by the guest
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Device ID, if this is a block or character device
+    pub fn rdev(&self) -> u64 {
+        self.rdev
+    }
+
+    /// Preferred block size for I/O
+    pub fn blksize(&self) -> u64 {
+        self.blksize
+    }
+
+    /// Number of 512-byte blocks allocated
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Last modification time
+    pub fn modified(&self) -> Result<SystemTime> {
+        Ok(self.mtime.to_system_time())
+    }
+
+    /// Last access time
+    pub fn accessed(&self) -> Result<SystemTime> {
+        Ok(self.atime.to_system_time())
+    }
+
+    /// Creation (birth) time
+    ///
+    /// libguestfs does not report the birth time of files, so this always fails with
+    /// [`io::ErrorKind::Unsupported`], like [`std::fs::Metadata::created`] on platforms without it.
+    /// See [`changed`](Self::changed) for the last status change time.
+    pub fn created(&self) -> Result<SystemTime> {
+        Err(Error::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "creation time is not available from libguestfs",
+        )))
+    }
+
+    /// Last status change time (`st_ctime`)
+    pub fn changed(&self) -> SystemTime {
+        self.ctime.to_system_time()
+    }
+}
+
+/// The type of a file inside the guest, see [`std::fs::FileType`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileType(u32);
+
+impl FileType {
+    /// Decode the `ftyp` character of a `guestfs_dirent`, `None` if the type is unknown
+    pub(crate) fn from_ftyp(ftyp: u8) -> Option<Self> {
+        Some(FileType(match ftyp {
+            b'b' => S_IFBLK,
+            b'c' => S_IFCHR,
+            b'd' => S_IFDIR,
+            b'f' => S_IFIFO,
+            b'l' => S_IFLNK,
+            b'r' => S_IFREG,
+            b's' => S_IFSOCK,
+            _ => return None,
+        }))
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.0 == S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.0 == S_IFREG
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.0 == S_IFLNK
+    }
+
+    pub fn is_block_device(&self) -> bool {
+        self.0 == S_IFBLK
+    }
+
+    pub fn is_char_device(&self) -> bool {
+        self.0 == S_IFCHR
+    }
+
+    pub fn is_fifo(&self) -> bool {
+        self.0 == S_IFIFO
+    }
+
+    pub fn is_socket(&self) -> bool {
+        self.0 == S_IFSOCK
+    }
+}
+
+/// Permission bits of a file inside the guest, see [`std::fs::Permissions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Permissions {
+    mode: u32,
+}
+
+impl Permissions {
+    /// Create permissions from Unix mode bits, e.g. `0o644`
+    pub fn from_mode(mode: u32) -> Self {
+        Self {
+            mode: mode & 0o7777,
+        }
+    }
+
+    /// The Unix mode bits, without the file type
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Set the Unix mode bits
+    pub fn set_mode(&mut self, mode: u32) {
+        self.mode = mode & 0o7777;
+    }
+
+    /// Whether nobody has write permission
+    pub fn readonly(&self) -> bool {
+        self.mode & 0o222 == 0
+    }
+
+    /// Remove all write bits, or add them back
+    ///
+    /// Like [`std::fs::Permissions::set_readonly`], `set_readonly(false)` makes the file
+    /// world-writable. Use [`set_mode`](Self::set_mode) for finer control.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        if readonly {
+            self.mode &= !0o222;
+        } else {
+            self.mode |= 0o222;
+        }
+    }
+
+    /// Whether the set-user-ID bit is set
+    pub fn is_setuid(&self) -> bool {
+        self.mode & S_ISUID != 0
+    }
+
+    /// Whether the set-group-ID bit is set
+    pub fn is_setgid(&self) -> bool {
+        self.mode & S_ISGID != 0
+    }
+
+    /// Whether the sticky bit is set
+    pub fn is_sticky(&self) -> bool {
+        self.mode & S_ISVTX != 0
+    }
+}
@@ -0,0 +1,699 @@
+//! High-level, [`std::fs`]-like interface to guest filesystems
+use crate::{
+    error::Error,
+    guestfs::{
+        CancelHandle, Configuring, Device, DriveOptions, Event, EventHandle, EventMask, GuestFs,
+        Launched, LogicalVolume, Mountable, Partition, Progress, SilenceErrors, State,
+        WithProgress,
+    },
+    Result,
+};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+mod file;
+mod inspect;
+mod metadata;
+mod mount;
+mod read_dir;
+
+pub use file::{GuestFile, OpenOptions};
+pub use inspect::{Application, Distro, OsInfo, OsType, PackageFormat, PackageManager};
+pub use metadata::{FileType, Metadata, Permissions};
+pub use mount::{MountFailure, MountMode, MountOptions, MountedOs};
+pub use read_dir::{DirEntry, ReadDir};
+
+// Ownership model
+//
+// All calls from the C FFI are monolithic, and are expected to run every operation from one big
+// god struct (`[GuestFs]`). This is not how the Rust filesystem API works, so we abstract it away:
+// `[GuestFileSystem]` owns the handle, and derivative objects such as `[GuestFile]` and `[Augeas]`
+// borrow the filesystem they were created from. The borrow checker makes sure they cannot outlive it,
+// and since mounting and unmounting take `&mut self`, the mounts cannot change while they are alive.
+//
+// suggestion: a `commit()` method that actually writes the changes to the filesystem? this will force all changes to be made at once though
+//
+// suggestion: some kind of method to also open a write handle to the file somehow and write it live too
+//
+
+/// An Augeas session on the filesystem
+///
+/// libguestfs supports only one Augeas session per handle, so only one [`Augeas`] can be alive
+/// at a time. The session is closed when this is dropped.
+///
+/// The session borrows the filesystem it was opened on, so it cannot outlive it:
+///
+/// ```compile_fail
+/// # use hvk::fs::{Augeas, GuestFileSystem};
+/// # fn f(fs: GuestFileSystem) {
+/// let aug = fs.augeas("/", 0).unwrap();
+/// drop(fs);
+/// aug.save().unwrap();
+/// # }
+/// ```
+pub struct Augeas<'fs> {
+    fs: &'fs GuestFileSystem<'fs>,
+    root: PathBuf,
+    // dropped after the session is closed
+    _session: SessionGuard<'fs>,
+}
+
+impl<'fs> Augeas<'fs> {
+    fn open(fs: &'fs GuestFileSystem<'fs>, root: &Path, flags: i32) -> Result<Self> {
+        let session = SessionGuard::acquire(&fs.augeas_open).ok_or_else(|| {
+            io_error(
+                io::ErrorKind::ResourceBusy,
+                "an Augeas session is already open on this filesystem",
+            )
+        })?;
+        // the guard marks the session as closed again if this fails
+        fs.inner().aug_init(root, flags)?;
+        Ok(Self {
+            fs,
+            root: root.into(),
+            _session: session,
+        })
+    }
+
+    /// The root of the filesystem Augeas was opened on
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the value of a single node
+    pub fn get(&self, augpath: &str) -> Result<String> {
+        self.fs.inner().aug_get(augpath)
+    }
+
+    /// Set the value of a single node
+    pub fn set(&self, augpath: &str, value: &str) -> Result<()> {
+        self.fs.inner().aug_set(augpath, value)
+    }
+
+    /// Clear the value of a node (similar to `augtool clear`)
+    pub fn clear(&self, augpath: &str) -> Result<()> {
+        self.fs.inner().aug_clear(augpath)
+    }
+
+    /// List the nodes matching a path expression
+    pub fn matches(&self, augpath: &str) -> Result<Box<[String]>> {
+        self.fs.inner().aug_match(augpath)
+    }
+
+    /// Write all pending changes to disk
+    pub fn save(&self) -> Result<()> {
+        self.fs.inner().aug_save()
+    }
+}
+
+impl Drop for Augeas<'_> {
+    fn drop(&mut self) {
+        // closing can only fail if the appliance is gone, in which case there is nothing left to close
+        let _ = self.fs.inner().aug_close();
+    }
+}
+
+/// Marks the Augeas session of a filesystem as open for as long as it is alive
+///
+/// This is kept apart from [`Augeas`] so the bookkeeping does not depend on the appliance.
+struct SessionGuard<'fs>(&'fs Cell<bool>);
+
+impl<'fs> SessionGuard<'fs> {
+    /// Mark the session as open, or return `None` if it already is
+    fn acquire(open: &'fs Cell<bool>) -> Option<Self> {
+        (!open.replace(true)).then(|| Self(open))
+    }
+}
+
+impl Drop for SessionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// ACL type for a file or directory
+pub enum AclType {
+    /// ordinary (access) ACL for any file, directory or other filesystem object.
+    Access,
+    /// default ACL. Normally this only makes sense if path is a directory.
+    Default,
+}
+
+impl FromStr for AclType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "access" => Self::Access,
+            "default" => Self::Default,
+            _ => return Err(s.into()),
+        })
+    }
+}
+
+impl AclType {
+    fn to_str(&self) -> &str {
+        match self {
+            AclType::Access => "access",
+            AclType::Default => "default",
+        }
+    }
+}
+
+impl std::fmt::Display for AclType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+/// High-level wrapper around libguestfs functions
+///
+/// Like [`GuestFs`], the filesystem starts out [`Configuring`] and has to be
+/// [launched](GuestFileSystem::launch) before it can be accessed.
+pub struct GuestFileSystem<'a, S: State = Launched> {
+    //guestfs: *mut guestfs,
+    inner: GuestFs<'a, S>,
+    // whether an `[Augeas]` session is currently open
+    augeas_open: Cell<bool>,
+}
+
+impl<'a, S: State> From<GuestFs<'a, S>> for GuestFileSystem<'a, S> {
+    fn from(guestfs: GuestFs<'a, S>) -> Self {
+        Self {
+            inner: guestfs,
+            augeas_open: Cell::new(false),
+        }
+    }
+}
+
+// Functions available in every phase
+impl<S: State> GuestFileSystem<'_, S> {
+    /// Call `handler` with the message of every error reported by libguestfs,
+    /// see [`GuestFs::set_error_handler`]
+    pub fn set_error_handler(&mut self, handler: impl FnMut(&str) + Send + 'static) {
+        self.inner.set_error_handler(handler)
+    }
+
+    /// Remove the handler set by [`set_error_handler`](Self::set_error_handler)
+    pub fn clear_error_handler(&mut self) {
+        self.inner.clear_error_handler()
+    }
+
+    /// Do not call the error handler until the returned guard is dropped,
+    /// see [`GuestFs::silence_errors`]
+    pub fn silence_errors(&self) -> SilenceErrors<'_> {
+        self.inner.silence_errors()
+    }
+
+    /// Call `handler` with every event of a type in `mask`, see [`GuestFs::on_event`]
+    pub fn on_event(
+        &self,
+        mask: EventMask,
+        handler: impl FnMut(&Event<'_>) + Send + 'static,
+    ) -> Result<EventHandle> {
+        self.inner.on_event(mask, handler)
+    }
+}
+
+impl<'a> GuestFileSystem<'a, Configuring> {
+    /// Create a new filesystem handle with the default settings
+    ///
+    /// Use [`GuestFs::builder`] and [`GuestFileSystem::from`] to configure the appliance.
+    pub fn new() -> Result<Self> {
+        GuestFs::new().map(Self::from)
+    }
+
+    /// Add a drive to the disk image
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - the drive to add, see [`DriveOptions`]
+    pub fn add_drive(&mut self, options: &DriveOptions) -> Result<()> {
+        self.inner.add_drive_opts(options)
+    }
+
+    /// Launch the libguestfs appliance, making the added drives accessible
+    pub fn launch(self) -> Result<GuestFileSystem<'a, Launched>> {
+        self.inner.launch().map(GuestFileSystem::from)
+    }
+}
+
+impl GuestFileSystem<'_, Launched> {
+    pub(crate) fn inner(&self) -> &GuestFs<'_> {
+        &self.inner
+    }
+
+    /// List the filesystems on the disk image
+    ///
+    /// # Returns
+    ///
+    /// A map of the filesystems on the disk image to their types, e.g. `ext4`
+    pub fn list_filesystems(&self) -> Result<BTreeMap<Mountable, String>> {
+        self.inner.list_filesystems()
+    }
+
+    /// List the disks, e.g. `/dev/sda`
+    pub fn list_devices(&self) -> Result<Box<[Device]>> {
+        self.inner.list_devices()
+    }
+
+    /// List the partitions on the disks, e.g. `/dev/sda1`
+    pub fn list_partitions(&self) -> Result<Box<[Partition]>> {
+        self.inner.list_partitions()
+    }
+
+    /// List the LVM logical volumes, e.g. `/dev/vg0/root`
+    pub fn lvs(&self) -> Result<Box<[LogicalVolume]>> {
+        self.inner.lvs()
+    }
+
+    /// The disk a partition is on, see [`GuestFs::part_to_dev`]
+    pub fn part_to_dev(&self, partition: &Partition) -> Result<Device> {
+        self.inner.part_to_dev(partition)
+    }
+
+    /// The number of a partition on its disk, see [`GuestFs::part_to_partnum`]
+    pub fn part_to_partnum(&self, partition: &Partition) -> Result<i32> {
+        self.inner.part_to_partnum(partition)
+    }
+
+    /// The canonical name of a device, see [`GuestFs::canonical_device_name`]
+    pub fn canonical_device_name(&self, device: impl AsRef<Device>) -> Result<Device> {
+        self.inner.canonical_device_name(device)
+    }
+
+    /// Mount a device to a mountpoint from the disk image
+    ///
+    /// # Arguments
+    ///
+    /// * `mountable` - the device or btrfs subvolume to mount
+    pub fn mount(
+        &mut self,
+        mountable: impl Into<Mountable>,
+        mountpoint: impl AsRef<Path>,
+    ) -> Result<()> {
+        self.inner.mount(mountable, mountpoint)
+    }
+
+    /// Unmount a device from a mountpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `mountpoint` - the mountpoint to unmount
+    pub fn umount(&mut self, mountpoint: impl AsRef<Path>) -> Result<()> {
+        self.inner.umount(mountpoint)
+    }
+
+    /// Unmount every mounted filesystem
+    pub fn umount_all(&mut self) -> Result<()> {
+        self.inner.umount_all()
+    }
+
+    /// List the devices of the mounted filesystems
+    pub fn mounts(&self) -> Result<Box<[Mountable]>> {
+        self.inner.mounts()
+    }
+
+    /// List the mounted filesystems, as a map of their mountpoints to their devices
+    ///
+    /// Parents sort before their children.
+    pub fn mountpoints(&self) -> Result<BTreeMap<PathBuf, Mountable>> {
+        self.inner.mountpoints()
+    }
+
+    /// Creates an empty file at the specified path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path to the file to create
+    pub fn touch(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.inner.touch(path)
+    }
+
+    /// Report the progress of the next long-running call to `observer`,
+    /// see [`GuestFs::with_progress`]
+    pub fn with_progress<F>(&self, observer: F) -> WithProgress<'_, F>
+    where
+        F: FnMut(&Progress) + Send + 'static,
+    {
+        self.inner.with_progress(observer)
+    }
+
+    /// Get a handle to cancel uploads and downloads from another thread,
+    /// see [`GuestFs::cancel_handle`]
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.inner.cancel_handle()
+    }
+
+    /// Open an Augeas session on the filesystem
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - the root of the filesystem to edit, usually `/`
+    /// * `flags` - a bitmask of the `AUG_*` flags, see `guestfs_aug_init`
+    pub fn augeas(&self, root: impl AsRef<Path>, flags: i32) -> Result<Augeas<'_>> {
+        Augeas::open(self, root.as_ref(), flags)
+    }
+
+    /// Shutdown the guestfs appliance
+    /// This is called automatically when the GuestFs object is dropped,
+    /// but you can call it manually if you'd like to handle errors
+    /// during shutdown.
+    pub fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    /// Get the ACL for a file or directory
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path to the file or directory
+    ///
+    /// * `acl_type` - the type of ACL to get
+    ///
+    /// # Returns
+    ///
+    /// an ACL string (e.g. "user::rwx,group::r--,other::r--")
+    pub fn acl_get_file(&self, path: impl AsRef<Path>, acl_type: AclType) -> Result<String> {
+        self.inner.acl_get_file(path, acl_type.to_str())
+    }
+
+    /// Set the ACL for a file or directory
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path to the file or directory
+    /// * `acl_type` - the type of ACL to set
+    /// * `acl` - the ACL string (e.g. "user::rwx,group::r--,other::r--")
+    pub fn acl_set_file(&self, path: impl AsRef<Path>, acl_type: AclType, acl: &str) -> Result<()> {
+        self.inner.acl_set_file(path, acl_type.to_str(), acl)
+    }
+}
+
+// number of names passed to a single `guestfs_lstatnslist` call
+const METADATA_BATCH_SIZE: usize = 1000;
+
+fn io_error(kind: io::ErrorKind, message: &str) -> Error {
+    Error::Io(io::Error::new(kind, message))
+}
+
+// Equivalents of the std::fs free functions, operating inside the guest
+impl GuestFileSystem<'_, Launched> {
+    /// Read the entire contents of a file into a bytes vector, see [`std::fs::read`]
+    ///
+    /// The file is streamed out of the appliance, so unlike [`GuestFs::read_file`] it may be
+    /// larger than the protocol message size limit.
+    pub fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        self.inner.download_to(path.as_ref(), &mut contents)?;
+        Ok(contents)
+    }
+
+    /// Read the entire contents of a file into a string, see [`std::fs::read_to_string`]
+    pub fn read_to_string(&self, path: impl AsRef<Path>) -> Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|_| {
+            io_error(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })
+    }
+
+    /// Write a slice as the entire contents of a file, see [`std::fs::write`]
+    ///
+    /// The file is created if it does not exist, and truncated if it does. The contents are
+    /// streamed into the appliance, so unlike [`GuestFs::write`] they may be larger than the
+    /// protocol message size limit.
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+        self.inner.upload_from(contents.as_ref(), path.as_ref())?;
+        Ok(())
+    }
+
+    /// Create a new, empty directory, see [`std::fs::create_dir`]
+    pub fn create_dir(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.inner.mkdir(path.as_ref())
+    }
+
+    /// Recursively create a directory and all of its missing parents, see [`std::fs::create_dir_all`]
+    pub fn create_dir_all(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.inner.mkdir_p(path.as_ref())
+    }
+
+    /// Remove a file, see [`std::fs::remove_file`]
+    pub fn remove_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.inner.rm(path.as_ref())
+    }
+
+    /// Remove an empty directory, see [`std::fs::remove_dir`]
+    pub fn remove_dir(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.inner.rmdir(path.as_ref())
+    }
+
+    /// Remove a directory after removing all of its contents, see [`std::fs::remove_dir_all`]
+    ///
+    /// Like std and unlike `rm -rf`, this fails if `path` does not exist or is not a directory.
+    /// If `path` is a symbolic link, the link itself is removed without following it.
+    pub fn remove_dir_all(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let metadata = {
+            let _quiet = self.silence_errors();
+            self.symlink_metadata(path)?
+        };
+        if metadata.is_symlink() {
+            return self.inner.rm(path);
+        }
+        if !metadata.is_dir() {
+            return Err(io_error(io::ErrorKind::NotADirectory, "not a directory"));
+        }
+        self.inner.rm_rf(path)
+    }
+
+    /// Rename a file or directory, replacing `to` if it already exists, see [`std::fs::rename`]
+    ///
+    /// This is built on `mv`, which would move `from` into an existing directory `to` and copy
+    /// it across filesystems, so what `rename(2)` rejects is checked first: `from` and `to` must
+    /// be on the same filesystem, a directory can only replace an empty directory, and nothing
+    /// else can replace a directory.
+    ///
+    /// Unlike in std, the checks, the removal of a replaced directory or symbolic link and the
+    /// move are separate calls to the appliance, so this is not atomic.
+    pub fn rename(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let source = self.symlink_metadata(from)?;
+        let parent = to
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("/"));
+        if self.metadata(parent)?.dev() != source.dev() {
+            return Err(io_error(
+                io::ErrorKind::CrossesDevices,
+                "cannot rename across filesystems",
+            ));
+        }
+
+        let target = {
+            let _quiet = self.silence_errors();
+            self.symlink_metadata(to).ok()
+        };
+        if let Some(target) = target {
+            if (target.dev(), target.ino()) == (source.dev(), source.ino()) {
+                // like rename(2), renaming a file onto itself does nothing
+                return Ok(());
+            }
+            match (source.is_dir(), target.is_dir()) {
+                (false, true) => {
+                    return Err(io_error(
+                        io::ErrorKind::IsADirectory,
+                        "the destination path is a directory",
+                    ))
+                }
+                (true, false) => {
+                    return Err(io_error(
+                        io::ErrorKind::NotADirectory,
+                        "the destination path is not a directory",
+                    ))
+                }
+                // fails unless the directory is empty, like rename(2)
+                (true, true) => self.inner.rmdir(to)?,
+                // mv would follow a link to a directory
+                (false, false) if target.is_symlink() => self.inner.rm(to)?,
+                (false, false) => {}
+            }
+        }
+        self.inner.mv(from, to)
+    }
+
+    /// Copy the contents of a file to another file, see [`std::fs::copy`]
+    ///
+    /// Like [`std::fs::copy`], the permission bits of `from` are copied as well, but not its
+    /// ownership, timestamps or extended attributes; use [`GuestFs::cp_a`] to preserve those.
+    /// `to` is overwritten if it exists, and must not be a directory.
+    /// Returns the number of bytes copied.
+    pub fn copy(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<u64> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let metadata = self.metadata(from)?;
+        if !metadata.is_file() {
+            return Err(io_error(
+                io::ErrorKind::InvalidInput,
+                "the source path is not an existing regular file",
+            ));
+        }
+        // cp would copy into the directory instead
+        let to_is_dir = {
+            let _quiet = self.silence_errors();
+            self.metadata(to).is_ok_and(|to| to.is_dir())
+        };
+        if to_is_dir {
+            return Err(io_error(
+                io::ErrorKind::IsADirectory,
+                "the destination path is a directory",
+            ));
+        }
+        self.inner.cp(from, to)?;
+        // cp keeps the mode of an existing destination
+        self.set_permissions(to, metadata.permissions())?;
+        Ok(metadata.len())
+    }
+
+    /// Create a new hard link, see [`std::fs::hard_link`]
+    ///
+    /// Like std, this fails with [`io::ErrorKind::AlreadyExists`] if `link` exists. Unlike in
+    /// std, the check and the creation are two separate calls to the appliance.
+    pub fn hard_link(&self, original: impl AsRef<Path>, link: impl AsRef<Path>) -> Result<()> {
+        self.check_new_link(link.as_ref())?;
+        self.inner.ln(original.as_ref(), link.as_ref())
+    }
+
+    /// Create a new symbolic link, see [`std::os::unix::fs::symlink`]
+    ///
+    /// Like std, this fails with [`io::ErrorKind::AlreadyExists`] if `link` exists. Unlike in
+    /// std, the check and the creation are two separate calls to the appliance.
+    pub fn symlink(&self, original: impl AsRef<Path>, link: impl AsRef<Path>) -> Result<()> {
+        self.check_new_link(link.as_ref())?;
+        self.inner.ln_s(original.as_ref(), link.as_ref())
+    }
+
+    /// Fail if `link` exists, since `ln` would create the link inside an existing directory
+    fn check_new_link(&self, link: &Path) -> Result<()> {
+        let exists = {
+            let _quiet = self.silence_errors();
+            self.symlink_metadata(link).is_ok()
+        };
+        if exists {
+            return Err(io_error(
+                io::ErrorKind::AlreadyExists,
+                "the link path already exists",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Read the target of a symbolic link, see [`std::fs::read_link`]
+    pub fn read_link(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        self.inner.readlink(path)
+    }
+
+    /// Return the canonical, absolute form of a path, see [`std::fs::canonicalize`]
+    pub fn canonicalize(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        self.inner.realpath(path)
+    }
+
+    /// Iterate over the entries of a directory, see [`std::fs::read_dir`]
+    ///
+    /// The `.` and `..` entries are skipped, see [`ReadDir::with_dots`].
+    pub fn read_dir(&self, path: impl AsRef<Path>) -> Result<ReadDir<'_>> {
+        let path = path.as_ref();
+        let list = self.inner.readdir(path)?;
+        Ok(ReadDir::new(self, path.to_path_buf(), list))
+    }
+
+    /// Query the metadata of a file, following symbolic links, see [`std::fs::metadata`]
+    pub fn metadata(&self, path: impl AsRef<Path>) -> Result<Metadata> {
+        let stat = self.inner.statns(path.as_ref())?;
+        Ok(Metadata::from_stat(&stat))
+    }
+
+    /// Query the metadata of a file without following symbolic links, see [`std::fs::symlink_metadata`]
+    pub fn symlink_metadata(&self, path: impl AsRef<Path>) -> Result<Metadata> {
+        let stat = self.inner.lstatns(path.as_ref())?;
+        Ok(Metadata::from_stat(&stat))
+    }
+
+    /// Query the metadata of many entries of a directory at once, without following symbolic links
+    ///
+    /// This is much faster than calling [`symlink_metadata`](Self::symlink_metadata) for each entry,
+    /// since the lookups are batched into a few round-trips to the appliance.
+    /// The result has one entry per name, `None` if the name could not be looked up.
+    pub fn symlink_metadata_many<N: AsRef<Path>>(
+        &self,
+        dir: impl AsRef<Path>,
+        names: &[N],
+    ) -> Result<Vec<Option<Metadata>>> {
+        let dir = dir.as_ref();
+        let mut metadata = Vec::with_capacity(names.len());
+        // keep every request well below the appliance message size limit
+        for chunk in names.chunks(METADATA_BATCH_SIZE) {
+            let stats = self.inner.lstatnslist(dir, chunk)?;
+            metadata.extend(
+                stats
+                    .entries()
+                    .iter()
+                    .map(|stat| (stat.st_ino != -1).then(|| Metadata::from_raw(stat))),
+            );
+        }
+        Ok(metadata)
+    }
+
+    /// Change the permissions of a file or directory, see [`std::fs::set_permissions`]
+    pub fn set_permissions(&self, path: impl AsRef<Path>, perm: Permissions) -> Result<()> {
+        self.inner.chmod(perm.mode() as i32, path.as_ref())
+    }
+
+    /// Check whether a path exists, following symbolic links, see [`std::fs::exists`]
+    pub fn exists(&self, path: impl AsRef<Path>) -> Result<bool> {
+        self.inner.exists(path.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn only_one_session_at_a_time() {
+        let open = Cell::new(false);
+        let session = SessionGuard::acquire(&open).unwrap();
+        assert!(open.get());
+        assert!(SessionGuard::acquire(&open).is_none());
+        // a failed attempt does not close the session
+        assert!(open.get());
+
+        drop(session);
+        assert!(!open.get());
+        assert!(SessionGuard::acquire(&open).is_some());
+        assert!(!open.get());
+    }
+
+    #[test]
+    fn session_is_closed_when_unwinding() {
+        let open = Cell::new(false);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _session = SessionGuard::acquire(&open).unwrap();
+            panic!("while the session is open");
+        }));
+        assert!(result.is_err());
+        assert!(!open.get());
+    }
+
+    #[test]
+    fn acl_types_round_trip() {
+        for name in ["access", "default"] {
+            assert_eq!(name.parse::<AclType>().unwrap().to_string(), name);
+        }
+        assert!("other".parse::<AclType>().is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Mounting the filesystems of an inspected operating system
+use super::{GuestFileSystem, OsInfo};
+use crate::{
+    error::Error,
+    guestfs::{Launched, Mountable},
+    Result,
+};
+use std::{
+    fmt,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// Whether to mount filesystems read-only or read-write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MountMode {
+    /// Mount read-only
+    ///
+    /// This alone is not safe on the disks of a running guest: ext3 and ext4 still replay a
+    /// dirty journal, which writes to the device, see [`MountOptions`]. Add such disks with
+    /// [`DriveOptions::readonly`](crate::guestfs::DriveOptions::readonly) so that no write
+    /// reaches them.
+    #[default]
+    ReadOnly,
+    /// Mount read-write
+    ReadWrite,
+}
+
+/// Options to configure how a filesystem is mounted, see [`GuestFileSystem::mount`] for plain
+/// read-write mounts
+///
+/// Mounting read-only does not stop ext3 and ext4 from replaying a dirty journal, which writes
+/// to the device; add the `noload` option to leave the journal alone.
+///
+/// ```ignore
+/// use hvk::{fs::MountOptions, guestfs::Device};
+///
+/// MountOptions::new()
+///     .read_only(true)
+///     .option("noload")
+///     .mount(&mut fs, Device::new("/dev/sda1")?, "/")?;
+/// MountOptions::new()
+///     .vfs_type("btrfs")
+///     .option("subvol=@home")
+///     .option("compress=zstd")
+///     .mount(&mut fs, Device::new("/dev/sda2")?, "/home")?;
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct MountOptions {
+    read_only: bool,
+    noexec: bool,
+    vfs_type: Option<String>,
+    options: Vec<String>,
+}
+
+impl MountOptions {
+    /// Create a blank set of options, for a plain read-write mount
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mount the filesystem read-only
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Do not allow executing files from the filesystem
+    pub fn noexec(mut self, noexec: bool) -> Self {
+        self.noexec = noexec;
+        self
+    }
+
+    /// Mount the filesystem as this type, e.g. `btrfs`, instead of detecting it
+    pub fn vfs_type(mut self, vfs_type: impl Into<String>) -> Self {
+        self.vfs_type = Some(vfs_type.into());
+        self
+    }
+
+    /// Add a raw mount option, e.g. `subvol=@home` or `compress=zstd`
+    ///
+    /// Options are passed to the kernel as they are, separated by commas.
+    pub fn option(mut self, option: impl Into<String>) -> Self {
+        self.options.push(option.into());
+        self
+    }
+
+    /// The options as a comma-separated string, e.g. `ro,noexec,subvol=@`
+    fn options_string(&self) -> String {
+        let flags = [(self.read_only, "ro"), (self.noexec, "noexec")];
+        let options: Vec<&str> = flags
+            .into_iter()
+            .filter_map(|(enabled, flag)| enabled.then_some(flag))
+            .chain(self.options.iter().map(String::as_str))
+            .collect();
+        options.join(",")
+    }
+
+    /// Mount `mountable` on `mountpoint` with these options
+    #[doc(alias = "guestfs_mount_options")]
+    #[doc(alias = "guestfs_mount_vfs")]
+    pub fn mount(
+        &self,
+        fs: &mut GuestFileSystem<'_, Launched>,
+        mountable: impl Into<Mountable>,
+        mountpoint: impl AsRef<Path>,
+    ) -> Result<()> {
+        let inner = &mut fs.inner;
+        let mountable = mountable.into();
+        let options = self.options_string();
+        match &self.vfs_type {
+            Some(vfs_type) => inner.mount_vfs(&options, vfs_type, mountable, mountpoint),
+            None if options == "ro" => inner.mount_ro(mountable, mountpoint),
+            None if options.is_empty() => inner.mount(mountable, mountpoint),
+            None => inner.mount_options(&options, mountable, mountpoint),
+        }
+    }
+}
+
+/// A filesystem of an operating system which could not be mounted, see [`MountedOs::failures`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct MountFailure {
+    /// Where the filesystem should have been mounted
+    pub mountpoint: PathBuf,
+    /// The device or btrfs subvolume of the filesystem
+    pub mountable: Mountable,
+    /// Why it could not be mounted
+    pub error: Error,
+}
+
+/// The filesystems of an operating system, mounted by [`GuestFileSystem::mount_os`]
+///
+/// It dereferences to the filesystem, so files can be accessed while it is alive. The
+/// filesystems are unmounted in reverse order when it is dropped, ignoring any error; use
+/// [`unmount`](Self::unmount) to find out about them.
+pub struct MountedOs<'fs, 'a> {
+    fs: &'fs mut GuestFileSystem<'a, Launched>,
+    // in mount order
+    mounted: Vec<PathBuf>,
+    failures: Vec<MountFailure>,
+}
+
+impl<'a> MountedOs<'_, 'a> {
+    /// The mountpoints which were mounted, in the order they were mounted in
+    pub fn mounted(&self) -> &[PathBuf] {
+        &self.mounted
+    }
+
+    /// The filesystems which could not be mounted, e.g. missing devices or mountpoints
+    pub fn failures(&self) -> &[MountFailure] {
+        &self.failures
+    }
+
+    /// Unmount the filesystems in reverse order
+    ///
+    /// Every filesystem is unmounted even if some fail to, and the first error is returned.
+    pub fn unmount(mut self) -> Result<()> {
+        self.unmount_all()
+    }
+
+    fn unmount_all(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        while let Some(mountpoint) = self.mounted.pop() {
+            if let Err(error) = self.fs.umount(&mountpoint) {
+                result = result.and(Err(error));
+            }
+        }
+        result
+    }
+}
+
+impl<'a> Deref for MountedOs<'_, 'a> {
+    type Target = GuestFileSystem<'a, Launched>;
+
+    fn deref(&self) -> &Self::Target {
+        self.fs
+    }
+}
+
+impl fmt::Debug for MountedOs<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MountedOs")
+            .field("mounted", &self.mounted)
+            .field("failures", &self.failures)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for MountedOs<'_, '_> {
+    fn drop(&mut self) {
+        // unmounting can only fail if the filesystem is busy or the appliance is gone,
+        // neither of which can be fixed here
+        let _ = self.unmount_all();
+    }
+}
+
+impl<'a> GuestFileSystem<'a, Launched> {
+    /// Mount the filesystems of an operating system found by [`inspect`](Self::inspect)
+    ///
+    /// The filesystems are mounted by depth, so that `/` is mounted before `/usr`, and
+    /// `/usr` before `/usr/local`. Filesystems which cannot be mounted, e.g. because their
+    /// device is missing, are skipped and reported by [`MountedOs::failures`]; only failing to
+    /// mount `/` is an error.
+    ///
+    /// ```ignore
+    /// let os = fs.inspect()?.remove(0);
+    /// let mounted = fs.mount_os(&os, MountMode::ReadOnly)?;
+    /// for failure in mounted.failures() {
+    ///     eprintln!("{}: {}", failure.mountpoint.display(), failure.error);
+    /// }
+    /// let release = mounted.read_to_string("/etc/os-release")?;
+    /// ```
+    pub fn mount_os(&mut self, os: &OsInfo, mode: MountMode) -> Result<MountedOs<'_, 'a>> {
+        let mut mountpoints: Vec<(&PathBuf, &Mountable)> = os.mountpoints.iter().collect();
+        // a stable sort keeps siblings in the map's order
+        mountpoints.sort_by_key(|(mountpoint, _)| mountpoint.components().count());
+
+        let mut mounted = MountedOs {
+            fs: self,
+            mounted: Vec::with_capacity(mountpoints.len()),
+            failures: Vec::new(),
+        };
+        let options = MountOptions::new().read_only(mode == MountMode::ReadOnly);
+        for (mountpoint, mountable) in mountpoints {
+            match options.mount(mounted.fs, mountable, mountpoint) {
+                Ok(()) => mounted.mounted.push(mountpoint.clone()),
+                Err(error) if mountpoint == Path::new("/") => return Err(error),
+                Err(error) => mounted.failures.push(MountFailure {
+                    mountpoint: mountpoint.clone(),
+                    mountable: mountable.clone(),
+                    error,
+                }),
+            }
+        }
+        Ok(mounted)
+    }
+}
@@ -0,0 +1,148 @@
+use super::{io_error, FileType, GuestFileSystem, Metadata};
+use crate::{guestfs::types::DirEntList, Result};
+use std::{
+    cell::RefCell,
+    ffi::{OsStr, OsString},
+    fmt, io,
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    rc::Rc,
+};
+
+// State shared between a `[ReadDir]` and the entries it yields
+struct Shared<'fs> {
+    fs: &'fs GuestFileSystem<'fs>,
+    dir: PathBuf,
+    names: Vec<OsString>,
+    // metadata of every entry, fetched in one batch the first time any entry needs it
+    metadata: RefCell<Option<Vec<Option<Metadata>>>>,
+}
+
+impl Shared<'_> {
+    fn metadata(&self, index: usize) -> Result<Metadata> {
+        let mut cache = self.metadata.borrow_mut();
+        if cache.is_none() {
+            *cache = Some(self.fs.symlink_metadata_many(&self.dir, &self.names)?);
+        }
+        cache
+            .as_ref()
+            .and_then(|metadata| metadata[index].clone())
+            .ok_or_else(|| io_error(io::ErrorKind::NotFound, "directory entry no longer exists"))
+    }
+}
+
+/// Iterator over the entries of a directory inside the guest, see [`std::fs::ReadDir`]
+///
+/// Returned by [`GuestFileSystem::read_dir`]. The whole directory is listed up front,
+/// so iterating never fails, but items are still wrapped in a [`Result`] to match std.
+pub struct ReadDir<'fs> {
+    shared: Rc<Shared<'fs>>,
+    entries: std::vec::IntoIter<(usize, u64, u8)>,
+    dots: bool,
+}
+
+impl<'fs> ReadDir<'fs> {
+    pub(super) fn new(fs: &'fs GuestFileSystem<'fs>, dir: PathBuf, list: DirEntList) -> Self {
+        let mut names = Vec::with_capacity(list.len());
+        let mut entries = Vec::with_capacity(list.len());
+        for (index, entry) in list.iter().enumerate() {
+            names.push(OsStr::from_bytes(entry.name().to_bytes()).to_os_string());
+            entries.push((index, entry.ino() as u64, entry.ftyp()));
+        }
+
+        Self {
+            shared: Rc::new(Shared {
+                fs,
+                dir,
+                names,
+                metadata: RefCell::new(None),
+            }),
+            entries: entries.into_iter(),
+            dots: false,
+        }
+    }
+
+    /// Also yield the `.` and `..` entries, which are skipped by default
+    pub fn with_dots(mut self, dots: bool) -> Self {
+        self.dots = dots;
+        self
+    }
+}
+
+impl<'fs> Iterator for ReadDir<'fs> {
+    type Item = Result<DirEntry<'fs>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, ino, ftyp) in self.entries.by_ref() {
+            let name = self.shared.names[index].as_bytes();
+            if !self.dots && (name == b"." || name == b"..") {
+                continue;
+            }
+            return Some(Ok(DirEntry {
+                shared: Rc::clone(&self.shared),
+                index,
+                ino,
+                file_type: FileType::from_ftyp(ftyp),
+            }));
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.entries.len()))
+    }
+}
+
+impl fmt::Debug for ReadDir<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadDir").field(&self.shared.dir).finish()
+    }
+}
+
+/// An entry of a directory inside the guest, see [`std::fs::DirEntry`]
+pub struct DirEntry<'fs> {
+    shared: Rc<Shared<'fs>>,
+    index: usize,
+    ino: u64,
+    file_type: Option<FileType>,
+}
+
+impl DirEntry<'_> {
+    /// The full path of the entry, i.e. the directory joined with [`file_name`](Self::file_name)
+    pub fn path(&self) -> PathBuf {
+        self.shared.dir.join(&self.shared.names[self.index])
+    }
+
+    /// The bare name of the entry, which may not be valid UTF-8
+    pub fn file_name(&self) -> OsString {
+        self.shared.names[self.index].clone()
+    }
+
+    /// The inode number of the entry
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// The type of the entry
+    ///
+    /// This is usually known from the directory listing itself. Otherwise the metadata is fetched.
+    pub fn file_type(&self) -> Result<FileType> {
+        match self.file_type {
+            Some(file_type) => Ok(file_type),
+            None => Ok(self.metadata()?.file_type()),
+        }
+    }
+
+    /// The metadata of the entry, without following symbolic links
+    ///
+    /// The first call fetches the metadata of every entry of the directory in a single batch.
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.shared.metadata(self.index)
+    }
+}
+
+impl fmt::Debug for DirEntry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DirEntry").field(&self.path()).finish()
+    }
+}
@@ -0,0 +1,266 @@
+//! Owned buffers and strings returned by libguestfs
+//!
+//! libguestfs allocates the buffers, strings and string lists it returns with `malloc`, and
+//! the caller has to release them with `free`. These wrappers take ownership of them and free
+//! them with the C allocator when dropped, never with the Rust one.
+use crate::Result;
+use std::{
+    ffi::{c_char, CStr, OsStr, OsString},
+    fmt,
+    ops::Deref,
+    os::unix::ffi::OsStrExt,
+    ptr::NonNull,
+};
+
+/// An owned buffer of bytes returned by libguestfs, e.g. by `guestfs_read_file`
+///
+/// The buffer may contain NUL bytes. It dereferences to `[u8]`.
+pub struct GuestBuf {
+    ptr: NonNull<c_char>,
+    len: usize,
+}
+
+impl GuestBuf {
+    /// Takes ownership of a buffer of `len` bytes returned by libguestfs, `None` if `ptr` is null
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must either be null, or be allocated with `malloc`, be valid for reads of `len`
+    /// bytes, and must not be freed by anyone else.
+    pub unsafe fn from_raw(ptr: *mut c_char, len: usize) -> Option<Self> {
+        NonNull::new(ptr).map(|ptr| Self { ptr, len })
+    }
+
+    /// Takes ownership of a NUL-terminated string returned by libguestfs, as a buffer of bytes
+    ///
+    /// The length is the length of the string, without the terminating NUL.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must either be null, or be a valid NUL-terminated string allocated with `malloc`,
+    /// and must not be freed by anyone else.
+    pub unsafe fn from_raw_cstr(ptr: *mut c_char) -> Option<Self> {
+        let ptr = NonNull::new(ptr)?;
+        let len = unsafe { CStr::from_ptr(ptr.as_ptr()) }.count_bytes();
+        Some(Self { ptr, len })
+    }
+
+    /// The contents of the buffer
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safety: the pointer is valid for `len` bytes for as long as `self` is
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr().cast(), self.len) }
+    }
+}
+
+impl Deref for GuestBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for GuestBuf {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for GuestBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuestBuf").field("len", &self.len).finish()
+    }
+}
+
+impl Drop for GuestBuf {
+    fn drop(&mut self) {
+        unsafe { libc::free(self.ptr.as_ptr().cast()) }
+    }
+}
+
+// Safety: the buffer is uniquely owned and never mutated
+unsafe impl Send for GuestBuf {}
+unsafe impl Sync for GuestBuf {}
+
+/// An owned NUL-terminated string returned by libguestfs, e.g. by `guestfs_readlink`
+///
+/// The string is not necessarily valid UTF-8. It dereferences to [`CStr`].
+pub struct GuestString {
+    ptr: NonNull<c_char>,
+}
+
+impl GuestString {
+    /// Takes ownership of a string returned by libguestfs, `None` if `ptr` is null
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must either be null, or be a valid NUL-terminated string allocated with `malloc`,
+    /// and must not be freed by anyone else.
+    pub unsafe fn from_raw(ptr: *mut c_char) -> Option<Self> {
+        NonNull::new(ptr).map(|ptr| Self { ptr })
+    }
+
+    /// The string as a [`CStr`]
+    pub fn as_c_str(&self) -> &CStr {
+        // Safety: the string is valid for as long as `self` is
+        unsafe { CStr::from_ptr(self.ptr.as_ptr()) }
+    }
+
+    /// The string as an [`OsStr`], without any UTF-8 conversion
+    pub fn as_os_str(&self) -> &OsStr {
+        OsStr::from_bytes(self.to_bytes())
+    }
+
+    /// Copy the string into an [`OsString`], without any UTF-8 conversion
+    pub fn to_os_string(&self) -> OsString {
+        self.as_os_str().to_os_string()
+    }
+}
+
+impl Deref for GuestString {
+    type Target = CStr;
+
+    fn deref(&self) -> &CStr {
+        self.as_c_str()
+    }
+}
+
+impl AsRef<CStr> for GuestString {
+    fn as_ref(&self) -> &CStr {
+        self.as_c_str()
+    }
+}
+
+impl fmt::Debug for GuestString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_c_str().fmt(f)
+    }
+}
+
+impl Drop for GuestString {
+    fn drop(&mut self) {
+        unsafe { libc::free(self.ptr.as_ptr().cast()) }
+    }
+}
+
+// Safety: the string is uniquely owned and never mutated
+unsafe impl Send for GuestString {}
+unsafe impl Sync for GuestString {}
+
+/// An owned NULL-terminated list of strings returned by libguestfs, e.g. by `guestfs_read_lines`
+///
+/// Both the strings and the list itself are freed when this is dropped.
+pub struct GuestStringList {
+    ptr: NonNull<*mut c_char>,
+    len: usize,
+}
+
+impl GuestStringList {
+    /// Takes ownership of a string list returned by libguestfs, `None` if `ptr` is null
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must either be null, or be a NULL-terminated array of valid NUL-terminated strings,
+    /// where the array and every string are allocated with `malloc`. None of them may be freed
+    /// by anyone else.
+    pub unsafe fn from_raw(ptr: *mut *mut c_char) -> Option<Self> {
+        let ptr = NonNull::new(ptr)?;
+        let mut len = 0;
+        // the list ends at the first null *element*, not at a null pointer to it
+        while !unsafe { *ptr.as_ptr().add(len) }.is_null() {
+            len += 1;
+        }
+        Some(Self { ptr, len })
+    }
+
+    fn as_slice(&self) -> &[*mut c_char] {
+        // Safety: the list has `len` non-null elements for as long as `self` is
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Number of strings in the list
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the list is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the string at `index`
+    pub fn get(&self, index: usize) -> Option<&CStr> {
+        self.as_slice()
+            .get(index)
+            .map(|&ptr| unsafe { CStr::from_ptr(ptr) })
+    }
+
+    /// Iterate over the strings in the list
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &CStr> + '_ {
+        self.as_slice()
+            .iter()
+            .map(|&ptr| unsafe { CStr::from_ptr(ptr) })
+    }
+
+    /// Iterate over the list as consecutive key-value pairs
+    ///
+    /// This is how libguestfs returns hashtables, e.g. from `guestfs_inspect_get_mountpoints`.
+    /// A trailing key without a value is ignored.
+    pub fn pairs(&self) -> impl Iterator<Item = (&CStr, &CStr)> + '_ {
+        self.as_slice()
+            .chunks_exact(2)
+            .map(|pair| unsafe { (CStr::from_ptr(pair[0]), CStr::from_ptr(pair[1])) })
+    }
+
+    /// Copy every string into an [`OsString`], without any UTF-8 conversion
+    pub fn to_os_strings(&self) -> Vec<OsString> {
+        self.iter()
+            .map(|string| OsStr::from_bytes(string.to_bytes()).to_os_string())
+            .collect()
+    }
+
+    /// Convert every string to a [`String`], replacing invalid UTF-8 sequences
+    pub fn to_strings_lossy(&self) -> Box<[String]> {
+        self.iter()
+            .map(|string| string.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Copy every string like [`to_strings_lossy`](Self::to_strings_lossy), wrapped by `wrap`
+    pub(crate) fn map_lossy<T>(&self, wrap: impl FnMut(String) -> T) -> Box<[T]> {
+        self.iter()
+            .map(|string| string.to_string_lossy().into_owned())
+            .map(wrap)
+            .collect()
+    }
+
+    /// Like [`map_lossy`](Self::map_lossy), failing on the first string `wrap` rejects
+    pub(crate) fn try_map_lossy<T>(
+        &self,
+        wrap: impl FnMut(String) -> Result<T>,
+    ) -> Result<Box<[T]>> {
+        self.iter()
+            .map(|string| string.to_string_lossy().into_owned())
+            .map(wrap)
+            .collect()
+    }
+}
+
+impl fmt::Debug for GuestStringList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Drop for GuestStringList {
+    fn drop(&mut self) {
+        for &string in self.as_slice() {
+            unsafe { libc::free(string.cast()) }
+        }
+        unsafe { libc::free(self.ptr.as_ptr().cast()) }
+    }
+}
+
+// Safety: the list is uniquely owned and never mutated
+unsafe impl Send for GuestStringList {}
+unsafe impl Sync for GuestStringList {}
@@ -0,0 +1,194 @@
+use super::{Configuring, GuestFs};
+use crate::{error::Error, Result};
+use std::path::PathBuf;
+
+/// Builder for a [`GuestFs`] handle
+///
+/// Settings that are not set explicitly are left at the libguestfs defaults.
+/// Unlike `guestfs_create`, the `LIBGUESTFS_*` environment variables are only applied
+/// if [`parse_environment`](Self::parse_environment) is enabled, in which case any
+/// explicit settings take precedence over the environment.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct GuestFsBuilder {
+    parse_environment: bool,
+    close_on_exit: bool,
+    backend: Option<String>,
+    memsize: Option<i32>,
+    smp: Option<i32>,
+    hv: Option<PathBuf>,
+    append: Option<String>,
+    network: Option<bool>,
+    autosync: Option<bool>,
+    recovery_proc: Option<bool>,
+    cachedir: Option<PathBuf>,
+    tmpdir: Option<PathBuf>,
+    verbose: Option<bool>,
+    trace: Option<bool>,
+}
+
+impl Default for GuestFsBuilder {
+    fn default() -> Self {
+        Self {
+            parse_environment: false,
+            close_on_exit: true,
+            backend: None,
+            memsize: None,
+            smp: None,
+            hv: None,
+            append: None,
+            network: None,
+            autosync: None,
+            recovery_proc: None,
+            cachedir: None,
+            tmpdir: None,
+            verbose: None,
+            trace: None,
+        }
+    }
+}
+
+impl GuestFsBuilder {
+    /// Apply the `LIBGUESTFS_*` environment variables before any explicit settings
+    pub fn parse_environment(mut self, parse_environment: bool) -> Self {
+        self.parse_environment = parse_environment;
+        self
+    }
+
+    /// Close the handle automatically when the program exits (the default)
+    pub fn close_on_exit(mut self, close_on_exit: bool) -> Self {
+        self.close_on_exit = close_on_exit;
+        self
+    }
+
+    /// Backend used to run the appliance, e.g. `direct` or `libvirt`
+    pub fn backend(mut self, backend: impl Into<String>) -> Self {
+        self.backend = Some(backend.into());
+        self
+    }
+
+    /// Memory size of the appliance, in megabytes
+    pub fn memsize(mut self, memsize: i32) -> Self {
+        self.memsize = Some(memsize);
+        self
+    }
+
+    /// Number of virtual CPUs of the appliance
+    pub fn smp(mut self, smp: i32) -> Self {
+        self.smp = Some(smp);
+        self
+    }
+
+    /// Hypervisor binary used to run the appliance
+    pub fn hv(mut self, hv: impl Into<PathBuf>) -> Self {
+        self.hv = Some(hv.into());
+        self
+    }
+
+    /// Extra options for the appliance kernel command line
+    pub fn append(mut self, append: impl Into<String>) -> Self {
+        self.append = Some(append.into());
+        self
+    }
+
+    /// Enable network access from the appliance
+    pub fn network(mut self, network: bool) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Sync and unmount the filesystems when the handle is closed
+    pub fn autosync(mut self, autosync: bool) -> Self {
+        self.autosync = Some(autosync);
+        self
+    }
+
+    /// Run a recovery process which kills the appliance if the program exits unexpectedly
+    pub fn recovery_proc(mut self, recovery_proc: bool) -> Self {
+        self.recovery_proc = Some(recovery_proc);
+        self
+    }
+
+    /// Directory used to cache the appliance
+    pub fn cachedir(mut self, cachedir: impl Into<PathBuf>) -> Self {
+        self.cachedir = Some(cachedir.into());
+        self
+    }
+
+    /// Directory used for temporary files
+    pub fn tmpdir(mut self, tmpdir: impl Into<PathBuf>) -> Self {
+        self.tmpdir = Some(tmpdir.into());
+        self
+    }
+
+    /// Enable verbose messages
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = Some(verbose);
+        self
+    }
+
+    /// Enable command traces
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Create the handle and apply the settings
+    #[doc(alias = "guestfs_create_flags")]
+    pub fn build<'a>(self) -> Result<GuestFs<'a, Configuring>> {
+        let mut flags = libguestfs_sys::GUESTFS_CREATE_NO_ENVIRONMENT;
+        if !self.close_on_exit {
+            flags |= libguestfs_sys::GUESTFS_CREATE_NO_CLOSE_ON_EXIT;
+        }
+
+        let handle = unsafe { libguestfs_sys::guestfs_create_flags(flags) };
+        if handle.is_null() {
+            return Err(Error::Create(std::io::Error::last_os_error()));
+        }
+        // from here on the handle is closed on error
+        // Safety: the handle was just created and is owned by nothing else
+        let mut g = unsafe { GuestFs::from_raw(handle) };
+
+        if self.parse_environment {
+            g.parse_environment()?;
+        }
+        if let Some(backend) = &self.backend {
+            g.set_backend(backend)?;
+        }
+        if let Some(memsize) = self.memsize {
+            g.set_memsize(memsize)?;
+        }
+        if let Some(smp) = self.smp {
+            g.set_smp(smp)?;
+        }
+        if let Some(hv) = &self.hv {
+            g.set_hv(hv)?;
+        }
+        if let Some(append) = &self.append {
+            g.set_append(append)?;
+        }
+        if let Some(network) = self.network {
+            g.set_network(network)?;
+        }
+        if let Some(autosync) = self.autosync {
+            g.set_autosync(autosync)?;
+        }
+        if let Some(recovery_proc) = self.recovery_proc {
+            g.set_recovery_proc(recovery_proc)?;
+        }
+        if let Some(cachedir) = &self.cachedir {
+            g.set_cachedir(cachedir)?;
+        }
+        if let Some(tmpdir) = &self.tmpdir {
+            g.set_tmpdir(tmpdir)?;
+        }
+        if let Some(verbose) = self.verbose {
+            g.set_verbose(verbose)?;
+        }
+        if let Some(trace) = self.trace {
+            g.set_trace(trace)?;
+        }
+
+        Ok(g)
+    }
+}
@@ -0,0 +1,151 @@
+//! Rust callbacks registered with libguestfs
+//!
+//! Every [`GuestFs`] owns a heap-allocated [`Callbacks`], whose address is passed to libguestfs
+//! as the `opaque` pointer of every callback, so it stays valid when the handle is moved.
+//! Callbacks must never unwind into C, so panics are caught and resumed once control is back in Rust.
+use super::{cancel::CancelState, events::EventRegistry, GuestFs, State};
+use libguestfs_sys::guestfs_h;
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    ffi::{c_char, c_void, CStr},
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    ptr::{self, NonNull},
+    sync::{Arc, Mutex},
+};
+
+type ErrorHandler = Box<dyn FnMut(&str) + Send>;
+
+/// State shared between a [`GuestFs`] and the callbacks it registered with libguestfs
+pub(super) struct Callbacks {
+    error_handler: RefCell<Option<ErrorHandler>>,
+    // the first panic of a callback, waiting to be resumed
+    panic: Cell<Option<Box<dyn Any + Send>>>,
+    pub(super) events: Arc<Mutex<EventRegistry>>,
+    pub(super) cancel: Arc<CancelState>,
+}
+
+impl Callbacks {
+    /// Allocate the callback state of `handle` and register the callbacks with libguestfs
+    ///
+    /// This replaces the default error handler of libguestfs, which prints every error to stderr.
+    /// The state must be freed with [`Callbacks::free`] after the handle is closed.
+    pub(super) fn install(handle: *mut guestfs_h) -> NonNull<Self> {
+        let callbacks = NonNull::from(Box::leak(Box::new(Self {
+            error_handler: RefCell::new(None),
+            panic: Cell::new(None),
+            events: EventRegistry::new(),
+            cancel: CancelState::new(handle),
+        })));
+        unsafe {
+            libguestfs_sys::guestfs_set_error_handler(
+                handle,
+                Some(error_callback),
+                callbacks.as_ptr().cast(),
+            );
+        }
+        callbacks
+    }
+
+    /// Free the callback state
+    ///
+    /// # Safety
+    ///
+    /// `callbacks` must come from [`Callbacks::install`], and its handle must be closed already.
+    pub(super) unsafe fn free(callbacks: NonNull<Self>) {
+        let callbacks = unsafe { Box::from_raw(callbacks.as_ptr()) };
+        // event handles may outlive the handle, but its closures must not
+        EventRegistry::close(&callbacks.events);
+    }
+
+    /// Run a user callback, catching any panic so it does not unwind into libguestfs
+    pub(super) fn catch(&self, callback: impl FnOnce()) {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(callback)) {
+            // keep the first panic, like a panic while panicking would
+            let first = self.panic.take().unwrap_or(payload);
+            self.panic.set(Some(first));
+        }
+    }
+
+    /// Resume the panic of a callback, if any
+    pub(super) fn resume_panic(&self) {
+        if let Some(payload) = self.panic.take() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+unsafe extern "C" fn error_callback(_g: *mut guestfs_h, opaque: *mut c_void, msg: *const c_char) {
+    // Safety: `opaque` is the `Callbacks` of the handle, which outlives it
+    let callbacks = unsafe { &*opaque.cast::<Callbacks>() };
+    // the error itself is picked up through `guestfs_last_error`, this only notifies the user
+    callbacks.catch(|| {
+        // a handler that somehow triggers another error is not called recursively
+        let Ok(mut handler) = callbacks.error_handler.try_borrow_mut() else {
+            return;
+        };
+        if let Some(handler) = handler.as_mut() {
+            let msg = if msg.is_null() {
+                "".into()
+            } else {
+                unsafe { CStr::from_ptr(msg) }.to_string_lossy()
+            };
+            handler(&msg);
+        }
+    });
+}
+
+/// Guard which silences the error handler while it is alive, see [`GuestFs::silence_errors`]
+#[must_use = "errors are only silenced while the guard is alive"]
+pub struct SilenceErrors<'g> {
+    handle: *mut guestfs_h,
+    _marker: PhantomData<&'g ()>,
+}
+
+impl Drop for SilenceErrors<'_> {
+    fn drop(&mut self) {
+        unsafe { libguestfs_sys::guestfs_pop_error_handler(self.handle) }
+    }
+}
+
+impl<S: State> GuestFs<'_, S> {
+    /// Call `handler` with the message of every error reported by libguestfs
+    ///
+    /// By default, errors are only returned as [`Error`](crate::Error)s and not reported anywhere
+    /// else, unlike the libguestfs default of printing them to stderr. This replaces any previous
+    /// handler. If the handler panics, the panic is resumed once the failed call returns.
+    ///
+    /// ```ignore
+    /// g.set_error_handler(|msg| eprintln!("libguestfs: {msg}"));
+    /// ```
+    #[doc(alias = "guestfs_set_error_handler")]
+    pub fn set_error_handler(&mut self, handler: impl FnMut(&str) + Send + 'static) {
+        *self.callbacks().error_handler.borrow_mut() = Some(Box::new(handler));
+    }
+
+    /// Remove the handler set by [`set_error_handler`](Self::set_error_handler)
+    pub fn clear_error_handler(&mut self) {
+        self.callbacks().error_handler.borrow_mut().take();
+    }
+
+    /// Do not call the error handler until the returned guard is dropped
+    ///
+    /// This is meant for calls which are expected to fail, e.g. probing whether a path exists.
+    /// Failed calls still return an [`Error`](crate::Error) as usual.
+    ///
+    /// ```ignore
+    /// let is_dir = {
+    ///     let _quiet = g.silence_errors();
+    ///     g.statns("/maybe").is_ok_and(|stat| ...)
+    /// };
+    /// ```
+    #[doc(alias = "guestfs_push_error_handler")]
+    pub fn silence_errors(&self) -> SilenceErrors<'_> {
+        unsafe { libguestfs_sys::guestfs_push_error_handler(self.handle, None, ptr::null_mut()) }
+        SilenceErrors {
+            handle: self.handle,
+            _marker: PhantomData,
+        }
+    }
+}
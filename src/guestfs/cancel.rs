@@ -0,0 +1,177 @@
+//! Cancellation and timeouts of blocking calls
+//!
+//! libguestfs can only cancel uploads and downloads, with `guestfs_user_cancel`, which is the
+//! one function that may be called from another thread while a call is in progress. Any other
+//! call can only be interrupted by killing the appliance, which leaves the handle unusable.
+use super::{GuestFs, Launched};
+use crate::{error::Error, Result};
+use libguestfs_sys::guestfs_h;
+use std::{
+    fmt, ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::Duration,
+};
+
+// how long a timed out call gets to return after its transfer is cancelled,
+// before the appliance is killed
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+struct RawHandle(*mut guestfs_h);
+
+// Safety: the handle is only used for `guestfs_user_cancel`, which is thread-safe
+unsafe impl Send for RawHandle {}
+
+/// Cancellation state of a handle, shared with its [`CancelHandle`]s
+pub(super) struct CancelState {
+    // null once the handle is closed
+    handle: Mutex<RawHandle>,
+    cancelled: AtomicBool,
+    timed_out: AtomicBool,
+}
+
+impl CancelState {
+    pub(super) fn new(handle: *mut guestfs_h) -> Arc<Self> {
+        Arc::new(Self {
+            handle: Mutex::new(RawHandle(handle)),
+            cancelled: AtomicBool::new(false),
+            timed_out: AtomicBool::new(false),
+        })
+    }
+
+    /// Stop using the handle, which must happen before it is closed
+    pub(super) fn close(&self) {
+        self.lock().0 = ptr::null_mut();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RawHandle> {
+        self.handle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn cancel(&self, flag: &AtomicBool) {
+        // holding the lock keeps the handle from being closed meanwhile
+        let handle = self.lock();
+        if !handle.0.is_null() {
+            flag.store(true, Ordering::SeqCst);
+            unsafe { libguestfs_sys::guestfs_user_cancel(handle.0) }
+        }
+    }
+
+    /// Forget about cancellations which happened before a new call starts
+    pub(super) fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Turn the error of a failed call into [`Error::TimedOut`] or [`Error::Cancelled`]
+    /// if that is why it failed
+    pub(super) fn classify(&self, error: Error) -> Error {
+        match error {
+            Error::GuestFs(error) if self.timed_out.load(Ordering::SeqCst) => {
+                Error::TimedOut(error)
+            }
+            Error::GuestFs(error) if self.cancelled.load(Ordering::SeqCst) => {
+                Error::Cancelled(error)
+            }
+            error => error,
+        }
+    }
+}
+
+/// A handle to cancel the upload or download in progress on a [`GuestFs`] from another thread
+///
+/// See [`GuestFs::cancel_handle`]. The handle may outlive the [`GuestFs`], it does nothing
+/// once that is closed.
+#[derive(Clone)]
+pub struct CancelHandle {
+    state: Arc<CancelState>,
+}
+
+impl CancelHandle {
+    /// Cancel the upload or download in progress, if any
+    ///
+    /// The cancelled call fails with [`Error::Cancelled`]. Other calls are not affected,
+    /// and neither are calls started afterwards.
+    #[doc(alias = "guestfs_user_cancel")]
+    pub fn cancel(&self) {
+        self.state.cancel(&self.state.cancelled);
+    }
+}
+
+impl fmt::Debug for CancelHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelHandle").finish_non_exhaustive()
+    }
+}
+
+impl GuestFs<'_, Launched> {
+    /// Get a handle to cancel uploads and downloads from another thread
+    ///
+    /// ```ignore
+    /// let cancel = g.cancel_handle();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(Duration::from_secs(60));
+    ///     cancel.cancel();
+    /// });
+    /// g.download("/var/lib/images/disk.img", "disk.img")?;
+    /// ```
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            state: Arc::clone(&self.callbacks().cancel),
+        }
+    }
+
+    /// Run `f`, giving up on the calls it makes once `timeout` expires
+    ///
+    /// When the deadline expires, the upload or download in progress is cancelled. If `f` still
+    /// has not returned a second later, the appliance is killed, which makes the call in progress
+    /// and every later call fail. Calls failing past the deadline return [`Error::TimedOut`].
+    ///
+    /// The appliance can only be killed if libguestfs knows its process, i.e. with the `direct`
+    /// backend. A handle which timed out should be dropped.
+    ///
+    /// ```ignore
+    /// let root = Device::new("/dev/sda1")?;
+    /// g.with_timeout(Duration::from_secs(30), |g| g.mount(&root, "/"))?;
+    /// ```
+    pub fn with_timeout<T>(
+        &mut self,
+        timeout: Duration,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let state = Arc::clone(&self.callbacks().cancel);
+        // the watchdog cannot look it up itself, since the call in progress holds the handle
+        let pid = {
+            let _quiet = self.silence_errors();
+            self.get_pid().ok()
+        };
+
+        state.timed_out.store(false, Ordering::SeqCst);
+        let result = thread::scope(|scope| {
+            let (done, wait) = mpsc::channel::<()>();
+            let watchdog_state = Arc::clone(&state);
+            scope.spawn(move || {
+                if wait.recv_timeout(timeout) != Err(RecvTimeoutError::Timeout) {
+                    return;
+                }
+                watchdog_state.cancel(&watchdog_state.timed_out);
+                if wait.recv_timeout(KILL_GRACE) != Err(RecvTimeoutError::Timeout) {
+                    return;
+                }
+                if let Some(pid) = pid {
+                    unsafe { libc::kill(pid, libc::SIGKILL) };
+                }
+            });
+
+            let result = f(self);
+            // wakes the watchdog up
+            drop(done);
+            result
+        });
+        state.timed_out.store(false, Ordering::SeqCst);
+        result
+    }
+}
@@ -0,0 +1,547 @@
+//! Typed device names
+//!
+//! libguestfs names devices by their path in the appliance, e.g. `/dev/sda1`, and filesystems by
+//! "mountables", which are either a device or a btrfs subvolume of one, e.g.
+//! `btrfsvol:/dev/sda2/root`. These types keep them apart from guest paths, which are
+//! [`Path`](std::path::Path)s: passing a guest path where a device is expected does not compile.
+//!
+//! ```
+//! use hvk::guestfs::{Device, Mountable, Partition};
+//!
+//! let root: Mountable = "btrfsvol:/dev/sda2/root".parse().unwrap();
+//! assert_eq!(root.device(), &Device::new("/dev/sda2").unwrap());
+//! assert_eq!(root.btrfs_volume(), Some("root"));
+//!
+//! let part: Partition = "/dev/nvme0n1p3".parse().unwrap();
+//! assert_eq!(part.disk(), Device::new("/dev/nvme0n1").unwrap());
+//! assert_eq!(part.number(), 3);
+//! assert!("/dev/nvme0n1".parse::<Partition>().is_err());
+//! assert!("/dev/md127".parse::<Partition>().is_err());
+//! assert!("/etc/fstab".parse::<Device>().is_err());
+//! ```
+//!
+//! Strings are not accepted where a device is expected, they have to be parsed first:
+//!
+//! ```compile_fail
+//! # use hvk::guestfs::{GuestFs, Launched};
+//! # fn f(g: &GuestFs<'_, Launched>) {
+//! g.mkfs("ext4", "/etc/fstab").unwrap();
+//! # }
+//! ```
+//!
+//! The parsing is purely syntactic. Use [`GuestFs::part_to_dev`](super::GuestFs::part_to_dev) and
+//! [`GuestFs::canonical_device_name`](super::GuestFs::canonical_device_name) to ask the appliance.
+use crate::{error::Error, Result};
+use std::{fmt, str::FromStr};
+
+const DEV: &str = "/dev/";
+const BTRFSVOL: &str = "btrfsvol:";
+
+fn invalid(kind: &str, name: &str) -> Error {
+    Error::InvalidDevice(format!("{name:?} is not a valid {kind}"))
+}
+
+/// Whether `name` is `prefix` followed by a number, e.g. `md127` for `md`
+fn numbered(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Whether `name` is a whole disk whose name ends with a number, e.g. `nvme0n1` or `mmcblk0`
+///
+/// Their partitions separate the partition number with a `p`, e.g. `nvme0n1p1`.
+fn numbered_disk(name: &str) -> bool {
+    let nvme = name
+        .strip_prefix("nvme")
+        .and_then(|rest| rest.split_once('n'))
+        .is_some_and(|(controller, namespace)| numbered(controller, "") && numbered(namespace, ""));
+    nvme || ["mmcblk", "md", "nbd", "loop"]
+        .iter()
+        .any(|prefix| numbered(name, prefix))
+}
+
+/// Whether `name` is a whole disk named by letters, e.g. `sda`, `vdb` or `xvdaa`
+///
+/// Their partitions simply append the partition number, e.g. `sda1`.
+fn lettered_disk(name: &str) -> bool {
+    ["sd", "hd", "vd", "xvd", "ubd"].iter().any(|prefix| {
+        name.strip_prefix(prefix)
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_lowercase()))
+    })
+}
+
+/// Split a partition name into its disk and its partition number, e.g. `sda1` into `sda` and `1`
+///
+/// Whole disks, including those whose names end with a number such as `nvme0n1`, are not
+/// partitions.
+fn split_partition(name: &str) -> Option<(&str, u32)> {
+    let head = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = name[head.len()..].parse().ok()?;
+    match head.strip_suffix('p') {
+        Some(disk) if numbered_disk(disk) => Some((disk, number)),
+        _ if lettered_disk(head) => Some((head, number)),
+        _ => None,
+    }
+}
+
+/// A device in the appliance, e.g. `/dev/sda`, `/dev/sda1` or `/dev/vg0/root`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Device(String);
+
+impl Device {
+    /// Check that `name` is a device path, i.e. in `/dev`
+    pub fn new(name: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        match name.strip_prefix(DEV) {
+            Some(rest) if !rest.is_empty() && !name.contains('\0') => Ok(Self(name)),
+            _ => Err(invalid("device", &name)),
+        }
+    }
+
+    /// Wrap a device name returned by libguestfs, which needs no checking
+    pub(crate) fn from_guestfs(name: String) -> Self {
+        Self(name)
+    }
+
+    /// The device path, e.g. `/dev/sda1`
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Convert the device into its path
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+/// A partition of a disk, e.g. `/dev/sda1` or `/dev/nvme0n1p1`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Partition {
+    device: Device,
+    /// The length of the disk path, e.g. `/dev/sda` in `/dev/sda1`
+    disk: usize,
+    number: u32,
+}
+
+impl Partition {
+    /// Check that `name` is the name of a partition, i.e. a disk name followed by a number
+    pub fn new(name: impl Into<String>) -> Result<Self> {
+        Self::from_device(Device::new(name)?)
+    }
+
+    /// Check a partition name returned by libguestfs
+    ///
+    /// The appliance only names partitions in the ways [`new`](Self::new) knows, but anything
+    /// else is an error rather than a partition without a disk.
+    pub(crate) fn from_guestfs(name: String) -> Result<Self> {
+        Self::from_device(Device::from_guestfs(name))
+    }
+
+    fn from_device(device: Device) -> Result<Self> {
+        let split = device
+            .as_str()
+            .strip_prefix(DEV)
+            .filter(|rest| !rest.contains('/'))
+            .and_then(split_partition);
+        match split {
+            Some((disk, number)) => Ok(Self {
+                disk: DEV.len() + disk.len(),
+                number,
+                device,
+            }),
+            None => Err(invalid("partition", device.as_str())),
+        }
+    }
+
+    /// The disk the partition is on, e.g. `/dev/sda` for `/dev/sda1`
+    pub fn disk(&self) -> Device {
+        Device(self.as_str()[..self.disk].to_owned())
+    }
+
+    /// The partition number, e.g. `1` for `/dev/sda1`
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// The partition path, e.g. `/dev/sda1`
+    pub fn as_str(&self) -> &str {
+        self.device.as_str()
+    }
+}
+
+/// An LVM logical volume, e.g. `/dev/vg0/root`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LogicalVolume {
+    device: Device,
+    /// The position of the `/` between the volume group and the logical volume
+    separator: usize,
+}
+
+impl LogicalVolume {
+    /// Check that `name` is the name of a logical volume, i.e. `/dev/VG/LV`
+    ///
+    /// Device mapper names such as `/dev/mapper/vg0-root` are not accepted, see
+    /// [`GuestFs::canonical_device_name`](super::GuestFs::canonical_device_name).
+    pub fn new(name: impl Into<String>) -> Result<Self> {
+        Self::from_device(Device::new(name)?)
+    }
+
+    /// Check a logical volume name returned by libguestfs, which are always `/dev/VG/LV`
+    pub(crate) fn from_guestfs(name: String) -> Result<Self> {
+        Self::from_device(Device::from_guestfs(name))
+    }
+
+    fn from_device(device: Device) -> Result<Self> {
+        let split = device
+            .as_str()
+            .strip_prefix(DEV)
+            .and_then(|rest| rest.split_once('/'));
+        match split {
+            Some((vg, lv))
+                if !vg.is_empty()
+                    && !lv.is_empty()
+                    && !lv.contains('/')
+                    && !matches!(vg, "mapper" | "disk") =>
+            {
+                Ok(Self {
+                    separator: DEV.len() + vg.len(),
+                    device,
+                })
+            }
+            _ => Err(invalid("logical volume", device.as_str())),
+        }
+    }
+
+    /// The name of the volume group, e.g. `vg0` for `/dev/vg0/root`
+    pub fn volume_group(&self) -> &str {
+        &self.as_str()[DEV.len()..self.separator]
+    }
+
+    /// The name of the logical volume in its group, e.g. `root` for `/dev/vg0/root`
+    pub fn name(&self) -> &str {
+        &self.as_str()[self.separator + 1..]
+    }
+
+    /// The logical volume path, e.g. `/dev/vg0/root`
+    pub fn as_str(&self) -> &str {
+        self.device.as_str()
+    }
+}
+
+/// Something which can be mounted: a device, or a btrfs subvolume of one
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Mountable {
+    /// A filesystem on a device, e.g. `/dev/sda1`
+    Device(Device),
+    /// A btrfs subvolume, e.g. `btrfsvol:/dev/sda2/root`
+    BtrfsVolume {
+        /// The device of the btrfs filesystem
+        device: Device,
+        /// The path of the subvolume in the filesystem, without a leading `/`
+        volume: String,
+    },
+}
+
+impl Mountable {
+    /// Wrap a mountable returned by libguestfs, keeping anything unexpected as a device
+    pub(crate) fn from_guestfs(name: String) -> Self {
+        name.parse()
+            .unwrap_or_else(|_| Self::Device(Device::from_guestfs(name)))
+    }
+
+    /// The device of the filesystem
+    pub fn device(&self) -> &Device {
+        match self {
+            Self::Device(device) | Self::BtrfsVolume { device, .. } => device,
+        }
+    }
+
+    /// The btrfs subvolume, if this is one
+    pub fn btrfs_volume(&self) -> Option<&str> {
+        match self {
+            Self::Device(_) => None,
+            Self::BtrfsVolume { volume, .. } => Some(volume),
+        }
+    }
+}
+
+impl FromStr for Device {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl FromStr for Partition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl FromStr for LogicalVolume {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl FromStr for Mountable {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some(rest) = s.strip_prefix(BTRFSVOL) else {
+            return Device::new(s).map(Self::Device);
+        };
+        let bad = || invalid("btrfs subvolume", s);
+        let name = rest.strip_prefix(DEV).ok_or_else(bad)?;
+        // the device is `/dev/NAME` or `/dev/mapper/NAME`, the rest is the subvolume
+        let (prefix, name) = match name.strip_prefix("mapper/") {
+            Some(name) => ("mapper/", name),
+            None => ("", name),
+        };
+        let (device, volume) = name.split_once('/').ok_or_else(bad)?;
+        let volume = volume.trim_start_matches('/');
+        if device.is_empty() || volume.is_empty() {
+            return Err(bad());
+        }
+        Ok(Self::BtrfsVolume {
+            device: Device::new(format!("{DEV}{prefix}{device}"))?,
+            volume: volume.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.device.fmt(f)
+    }
+}
+
+impl fmt::Display for LogicalVolume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.device.fmt(f)
+    }
+}
+
+impl fmt::Display for Mountable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Device(device) => device.fmt(f),
+            Self::BtrfsVolume { device, volume } => write!(f, "{BTRFSVOL}{device}/{volume}"),
+        }
+    }
+}
+
+impl AsRef<Device> for Device {
+    fn as_ref(&self) -> &Device {
+        self
+    }
+}
+
+impl AsRef<Device> for Partition {
+    fn as_ref(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl AsRef<Device> for LogicalVolume {
+    fn as_ref(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl From<Partition> for Device {
+    fn from(partition: Partition) -> Self {
+        partition.device
+    }
+}
+
+impl From<LogicalVolume> for Device {
+    fn from(lv: LogicalVolume) -> Self {
+        lv.device
+    }
+}
+
+impl From<Device> for Mountable {
+    fn from(device: Device) -> Self {
+        Self::Device(device)
+    }
+}
+
+impl From<Partition> for Mountable {
+    fn from(partition: Partition) -> Self {
+        Self::Device(partition.device)
+    }
+}
+
+impl From<LogicalVolume> for Mountable {
+    fn from(lv: LogicalVolume) -> Self {
+        Self::Device(lv.device)
+    }
+}
+
+impl From<&Device> for Mountable {
+    fn from(device: &Device) -> Self {
+        Self::Device(device.clone())
+    }
+}
+
+impl From<&Partition> for Mountable {
+    fn from(partition: &Partition) -> Self {
+        Self::Device(partition.device.clone())
+    }
+}
+
+impl From<&LogicalVolume> for Mountable {
+    fn from(lv: &LogicalVolume) -> Self {
+        Self::Device(lv.device.clone())
+    }
+}
+
+impl From<&Mountable> for Mountable {
+    fn from(mountable: &Mountable) -> Self {
+        mountable.clone()
+    }
+}
+
+impl PartialEq<str> for Device {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Device {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<str> for Mountable {
+    fn eq(&self, other: &str) -> bool {
+        match self {
+            Self::Device(device) => device == other,
+            Self::BtrfsVolume { device, volume } => {
+                other
+                    .strip_prefix(BTRFSVOL)
+                    .and_then(|rest| rest.strip_prefix(device.as_str()))
+                    .and_then(|rest| rest.strip_prefix('/'))
+                    == Some(volume.as_str())
+            }
+        }
+    }
+}
+
+impl PartialEq<&str> for Mountable {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partitions_are_split_from_their_disk() {
+        for (name, disk, number) in [
+            ("sda1", "sda", 1),
+            ("sdab12", "sdab", 12),
+            ("hda2", "hda", 2),
+            ("vdb3", "vdb", 3),
+            ("xvda1", "xvda", 1),
+            ("ubda1", "ubda", 1),
+            ("nvme0n1p3", "nvme0n1", 3),
+            ("nvme12n34p56", "nvme12n34", 56),
+            ("mmcblk0p1", "mmcblk0", 1),
+            ("md127p1", "md127", 1),
+            ("nbd0p2", "nbd0", 2),
+            ("loop0p1", "loop0", 1),
+        ] {
+            assert_eq!(split_partition(name), Some((disk, number)), "{name}");
+        }
+    }
+
+    #[test]
+    fn whole_disks_are_not_partitions() {
+        for name in [
+            "sda", "vda", "xvda", "nvme0n1", "nvme1n12", "mmcblk0", "mmcblk10", "md127", "nbd0",
+            "loop0", "dm-0", "sr0", "ram0", "zram0",
+        ] {
+            assert_eq!(split_partition(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn malformed_names_are_not_partitions() {
+        for name in [
+            "",
+            "1",
+            "p1",
+            "sd1",
+            "sdA1",
+            "sda1p2",
+            "nvme0p1",
+            "nvmen1p1",
+            "nvme0n1p",
+            "mmcblkp1",
+            "mmcblk0p",
+            "sda99999999999",
+        ] {
+            assert_eq!(split_partition(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn partition_paths() {
+        let partition = Partition::new("/dev/nvme0n1p3").unwrap();
+        assert_eq!(partition.as_str(), "/dev/nvme0n1p3");
+        assert_eq!(partition.disk(), "/dev/nvme0n1");
+        assert_eq!(partition.number(), 3);
+        assert!(Partition::new("/dev/nvme0n1").is_err());
+        assert!(Partition::new("/dev/mmcblk0").is_err());
+        assert!(Partition::new("/dev/disk/sda1").is_err());
+        assert!(Partition::new("sda1").is_err());
+    }
+
+    #[test]
+    fn names_from_guestfs_are_checked() {
+        let partition = Partition::from_guestfs("/dev/sdb2".into()).unwrap();
+        assert_eq!(partition.disk(), "/dev/sdb");
+        assert_eq!(partition.number(), 2);
+        assert!(Partition::from_guestfs("/dev/sdb".into()).is_err());
+        assert!(Partition::from_guestfs("/dev/nvme0n1".into()).is_err());
+
+        let lv = LogicalVolume::from_guestfs("/dev/vg0/root".into()).unwrap();
+        assert_eq!(lv.volume_group(), "vg0");
+        assert_eq!(lv.name(), "root");
+        assert!(LogicalVolume::from_guestfs("/dev/mapper/vg0-root".into()).is_err());
+        assert!(LogicalVolume::from_guestfs("/dev/sda1".into()).is_err());
+    }
+
+    #[test]
+    fn unchecked_names_from_guestfs_do_not_panic() {
+        for name in [
+            "",
+            "/",
+            "/dev",
+            "/dev/",
+            "sda1",
+            "/dex/sda1",
+            "/a\u{e9}\u{e9}1",
+            "/a\u{e9}\u{e9}/x",
+        ] {
+            assert!(Partition::from_guestfs(name.into()).is_err(), "{name}");
+            assert!(LogicalVolume::from_guestfs(name.into()).is_err(), "{name}");
+        }
+    }
+}
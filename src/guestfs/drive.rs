@@ -0,0 +1,752 @@
+//! Typed optional arguments for `guestfs_add_drive_opts`
+use super::ffi_utils;
+use crate::{error::Error, Result};
+use std::{
+    ffi::{CString, OsStr},
+    fmt,
+    net::Ipv6Addr,
+    os::{
+        raw::{c_char, c_int},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+// the macro recurses into itself by name, so it has to be in scope
+use variyak::call_variadic;
+
+/// Disk image format of a drive
+///
+/// There is deliberately no default: letting libguestfs probe the format of an untrusted
+/// raw image is a security issue, since the guest can write a header that makes the image
+/// look like a qcow2 file backed by an arbitrary host file.
+/// Use [`DriveFormat::Probe`] only for images you trust.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriveFormat {
+    Raw,
+    Qcow2,
+    Vmdk,
+    Vdi,
+    Vhdx,
+    /// Any other format supported by qemu
+    Other(String),
+    /// Let libguestfs detect the format
+    Probe,
+}
+
+impl DriveFormat {
+    fn as_str(&self) -> Option<&str> {
+        Some(match self {
+            DriveFormat::Raw => "raw",
+            DriveFormat::Qcow2 => "qcow2",
+            DriveFormat::Vmdk => "vmdk",
+            DriveFormat::Vdi => "vdi",
+            DriveFormat::Vhdx => "vhdx",
+            DriveFormat::Other(format) => format,
+            DriveFormat::Probe => return None,
+        })
+    }
+}
+
+/// Host-side caching of a drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Writes are flushed to the host disk (the default)
+    Writeback,
+    /// Writes are not flushed. Much faster, but the image may be corrupted if the host crashes
+    Unsafe,
+}
+
+impl CacheMode {
+    fn as_str(&self) -> &str {
+        match self {
+            CacheMode::Writeback => "writeback",
+            CacheMode::Unsafe => "unsafe",
+        }
+    }
+}
+
+/// Whether discard (TRIM) requests from the guest are passed to the underlying image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Discard {
+    /// Discard is not enabled (the default)
+    Disable,
+    /// Discard is enabled, adding the drive fails if it is not supported
+    Enable,
+    /// Discard is enabled if possible
+    BestEffort,
+}
+
+impl Discard {
+    fn as_str(&self) -> &str {
+        match self {
+            Discard::Disable => "disable",
+            Discard::Enable => "enable",
+            Discard::BestEffort => "besteffort",
+        }
+    }
+}
+
+/// Network protocol used to access a remote drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Ftp,
+    Ftps,
+    /// GlusterFS, the path is `volume/image`
+    Gluster,
+    Http,
+    Https,
+    /// iSCSI, the path is `target-iqn/lun`
+    Iscsi,
+    /// Network Block Device, the path is the export name
+    Nbd,
+    /// Ceph RBD, the path is `pool/image`
+    Rbd,
+    /// Sheepdog, the path is the volume name
+    Sheepdog,
+    Ssh,
+    Tftp,
+}
+
+impl Protocol {
+    fn as_str(&self) -> &str {
+        match self {
+            Protocol::Ftp => "ftp",
+            Protocol::Ftps => "ftps",
+            Protocol::Gluster => "gluster",
+            Protocol::Http => "http",
+            Protocol::Https => "https",
+            Protocol::Iscsi => "iscsi",
+            Protocol::Nbd => "nbd",
+            Protocol::Rbd => "rbd",
+            Protocol::Sheepdog => "sheepdog",
+            Protocol::Ssh => "ssh",
+            Protocol::Tftp => "tftp",
+        }
+    }
+
+    /// Whether the protocol can be used without any server (e.g. using a ceph configuration file)
+    fn allows_no_server(&self) -> bool {
+        matches!(self, Protocol::Rbd | Protocol::Sheepdog)
+    }
+
+    /// Whether the protocol accepts more than one server
+    fn allows_many_servers(&self) -> bool {
+        matches!(self, Protocol::Rbd | Protocol::Sheepdog)
+    }
+
+    fn allows_unix_socket(&self) -> bool {
+        matches!(self, Protocol::Gluster | Protocol::Nbd)
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Address of a server hosting a remote drive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Server {
+    /// A TCP server, using the default port of the protocol if `port` is `None`
+    Tcp { host: String, port: Option<u16> },
+    /// A Unix domain socket on the host
+    Unix(PathBuf),
+}
+
+impl Server {
+    /// A TCP server
+    pub fn tcp(host: impl Into<String>, port: impl Into<Option<u16>>) -> Self {
+        Server::Tcp {
+            host: host.into(),
+            port: port.into(),
+        }
+    }
+
+    /// A Unix domain socket
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        Server::Unix(path.into())
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Server::Tcp { host, .. } if host.is_empty() => {
+                Err(Error::InvalidDrive("server host is empty".into()))
+            }
+            Server::Unix(path) if path.as_os_str().is_empty() => {
+                Err(Error::InvalidDrive("server socket path is empty".into()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Formats the server the way libguestfs expects it: `host`, `host:port`, `[ipv6]:port` or `unix:/path`
+impl fmt::Display for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Server::Tcp { host, port } => {
+                if host.parse::<Ipv6Addr>().is_ok() {
+                    write!(f, "[{host}]")?;
+                } else {
+                    f.write_str(host)?;
+                }
+                if let Some(port) = port {
+                    write!(f, ":{port}")?;
+                }
+                Ok(())
+            }
+            Server::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Parses a libguestfs server string, the inverse of the [`Display`](fmt::Display) implementation
+///
+/// The `tcp:` prefix is accepted as well.
+impl FromStr for Server {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            let server = Server::unix(path);
+            server.validate()?;
+            return Ok(server);
+        }
+
+        let s = s.strip_prefix("tcp:").unwrap_or(s);
+        let invalid = || Error::InvalidDrive(format!("invalid server: {s}"));
+        let parse_port = |port: &str| port.parse::<u16>().map_err(|_| invalid());
+
+        let server = if let Some(rest) = s.strip_prefix('[') {
+            // [ipv6] or [ipv6]:port
+            let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+            let port = match rest {
+                "" => None,
+                _ => Some(parse_port(rest.strip_prefix(':').ok_or_else(invalid)?)?),
+            };
+            Server::tcp(host, port)
+        } else if s.parse::<Ipv6Addr>().is_ok() {
+            Server::tcp(s, None)
+        } else {
+            match s.split_once(':') {
+                Some((host, port)) => Server::tcp(host, parse_port(port)?),
+                None => Server::tcp(s, None),
+            }
+        };
+        server.validate()?;
+        Ok(server)
+    }
+}
+
+/// Where the data of a drive comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriveSource {
+    /// A local disk image or block device
+    File(PathBuf),
+    /// An NBD export, e.g. served by `nbdkit` or `qemu-nbd`
+    Nbd {
+        server: Server,
+        /// Export name, empty for the default export
+        export: String,
+    },
+    /// A drive accessed over any other network protocol
+    Remote {
+        protocol: Protocol,
+        servers: Vec<Server>,
+        /// Protocol-specific path of the drive, see [`Protocol`]
+        path: String,
+    },
+}
+
+impl DriveSource {
+    /// The default export of an NBD server
+    pub fn nbd(server: Server) -> Self {
+        DriveSource::Nbd {
+            server,
+            export: String::new(),
+        }
+    }
+
+    /// A named export of an NBD server
+    pub fn nbd_export(server: Server, export: impl Into<String>) -> Self {
+        DriveSource::Nbd {
+            server,
+            export: export.into(),
+        }
+    }
+
+    /// A drive on a single remote server
+    pub fn remote(protocol: Protocol, server: Server, path: impl Into<String>) -> Self {
+        DriveSource::Remote {
+            protocol,
+            servers: vec![server],
+            path: path.into(),
+        }
+    }
+
+    fn protocol(&self) -> Option<Protocol> {
+        match self {
+            DriveSource::File(_) => None,
+            DriveSource::Nbd { .. } => Some(Protocol::Nbd),
+            DriveSource::Remote { protocol, .. } => Some(*protocol),
+        }
+    }
+
+    fn servers(&self) -> &[Server] {
+        match self {
+            DriveSource::File(_) => &[],
+            DriveSource::Nbd { server, .. } => std::slice::from_ref(server),
+            DriveSource::Remote { servers, .. } => servers,
+        }
+    }
+
+    /// Check that the servers are valid for the protocol
+    ///
+    /// This is called by [`GuestFs::add_drive_opts`](super::GuestFs::add_drive_opts),
+    /// so invalid sources are rejected before they reach libguestfs.
+    pub fn validate(&self) -> Result<()> {
+        let Some(protocol) = self.protocol() else {
+            return Ok(());
+        };
+        let servers = self.servers();
+        if servers.is_empty() && !protocol.allows_no_server() {
+            return Err(Error::InvalidDrive(format!(
+                "protocol {protocol} requires a server"
+            )));
+        }
+        if servers.len() > 1 && !protocol.allows_many_servers() {
+            return Err(Error::InvalidDrive(format!(
+                "protocol {protocol} accepts only one server"
+            )));
+        }
+        for server in servers {
+            server.validate()?;
+            if matches!(server, Server::Unix(_)) && !protocol.allows_unix_socket() {
+                return Err(Error::InvalidDrive(format!(
+                    "protocol {protocol} does not support Unix domain sockets"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn filename(&self) -> Result<CString> {
+        match self {
+            DriveSource::File(path) => ffi_utils::path_to_cstring(path),
+            DriveSource::Nbd { export: path, .. } | DriveSource::Remote { path, .. } => {
+                Ok(CString::new(path.as_str())?)
+            }
+        }
+    }
+}
+
+impl From<PathBuf> for DriveSource {
+    fn from(path: PathBuf) -> Self {
+        DriveSource::File(path)
+    }
+}
+
+impl From<&Path> for DriveSource {
+    fn from(path: &Path) -> Self {
+        DriveSource::File(path.to_path_buf())
+    }
+}
+
+impl From<&str> for DriveSource {
+    fn from(path: &str) -> Self {
+        DriveSource::File(path.into())
+    }
+}
+
+impl From<String> for DriveSource {
+    fn from(path: String) -> Self {
+        DriveSource::File(path.into())
+    }
+}
+
+/// Options for adding a drive with [`GuestFs::add_drive_opts`](super::GuestFs::add_drive_opts)
+///
+/// The [`secret`](Self::secret) is redacted in the [`Debug`](fmt::Debug) output.
+#[derive(Clone)]
+#[must_use]
+pub struct DriveOptions {
+    source: DriveSource,
+    format: DriveFormat,
+    username: Option<String>,
+    secret: Option<String>,
+    readonly: Option<bool>,
+    iface: Option<String>,
+    name: Option<String>,
+    label: Option<String>,
+    cachemode: Option<CacheMode>,
+    discard: Option<Discard>,
+    copyonread: Option<bool>,
+    blocksize: Option<i32>,
+}
+
+impl fmt::Debug for DriveOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DriveOptions")
+            .field("source", &self.source)
+            .field("format", &self.format)
+            .field("username", &self.username)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("readonly", &self.readonly)
+            .field("iface", &self.iface)
+            .field("name", &self.name)
+            .field("label", &self.label)
+            .field("cachemode", &self.cachemode)
+            .field("discard", &self.discard)
+            .field("copyonread", &self.copyonread)
+            .field("blocksize", &self.blocksize)
+            .finish()
+    }
+}
+
+// Maximum number of variadic arguments passed to guestfs_add_drive_opts, one code and one value per option
+const MAX_OPTARGS: usize = 26;
+
+impl DriveOptions {
+    /// Create options for a drive with the given format
+    ///
+    /// `source` is usually the path of a local disk image, see [`DriveSource`] for remote drives.
+    pub fn new(source: impl Into<DriveSource>, format: DriveFormat) -> Self {
+        Self {
+            source: source.into(),
+            format,
+            username: None,
+            secret: None,
+            readonly: None,
+            iface: None,
+            name: None,
+            label: None,
+            cachemode: None,
+            discard: None,
+            copyonread: None,
+            blocksize: None,
+        }
+    }
+
+    /// Add the drive in read-only mode
+    pub fn readonly(mut self, readonly: bool) -> Self {
+        self.readonly = Some(readonly);
+        self
+    }
+
+    /// User name used to authenticate to a remote server
+    pub fn username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    /// Secret used to authenticate to a remote server, e.g. the ceph `key` for RBD
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Emulated interface of the drive, e.g. `virtio` or `ide`
+    pub fn iface(mut self, iface: impl Into<String>) -> Self {
+        self.iface = Some(iface.into());
+        self
+    }
+
+    /// Name of the drive as seen by the hypervisor, for use with `guestfs_inspect_get_drive_mappings`
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Label of the drive, making it available as `/dev/disk/guestfs/<label>` in the appliance
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Host-side caching mode of the drive
+    pub fn cachemode(mut self, cachemode: CacheMode) -> Self {
+        self.cachemode = Some(cachemode);
+        self
+    }
+
+    /// Pass discard requests from the guest on to the image
+    pub fn discard(mut self, discard: Discard) -> Self {
+        self.discard = Some(discard);
+        self
+    }
+
+    /// Cache blocks read from a slow remote image in the local overlay
+    pub fn copyonread(mut self, copyonread: bool) -> Self {
+        self.copyonread = Some(copyonread);
+        self
+    }
+
+    /// Logical and physical block size of the drive, either 512 or 4096
+    pub fn blocksize(mut self, blocksize: i32) -> Self {
+        self.blocksize = Some(blocksize);
+        self
+    }
+
+    /// Check the options and convert them into the arguments of `guestfs_add_drive_opts`
+    pub(super) fn to_args(&self) -> Result<AddDriveArgs> {
+        self.source.validate()?;
+        if self.source.protocol().is_none() && (self.username.is_some() || self.secret.is_some()) {
+            return Err(Error::InvalidDrive(
+                "username and secret require a remote drive".into(),
+            ));
+        }
+        let filename = self.source.filename()?;
+
+        // The string arguments and the server list have to outlive the call
+        let mut strings = Vec::new();
+        let mut server_list: Vec<*const c_char> = Vec::new();
+        let mut string_arg = |value: &str| -> Result<isize> {
+            let value = CString::new(value)?;
+            let ptr = value.as_ptr() as isize;
+            strings.push(value);
+            Ok(ptr)
+        };
+
+        // Every optarg is passed as one pointer-sized argument: `int` values are promoted to a full
+        // register or stack slot in a variadic call, so this matches what libguestfs reads with va_arg
+        let mut args: Vec<isize> = Vec::with_capacity(MAX_OPTARGS);
+        if let Some(readonly) = self.readonly {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_READONLY as isize);
+            args.push(readonly.into());
+        }
+        if let Some(format) = self.format.as_str() {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_FORMAT as isize);
+            args.push(string_arg(format)?);
+        }
+        if let Some(protocol) = self.source.protocol() {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_PROTOCOL as isize);
+            args.push(string_arg(protocol.as_str())?);
+        }
+        if !self.source.servers().is_empty() {
+            for server in self.source.servers() {
+                server_list.push(string_arg(&server.to_string())? as *const c_char);
+            }
+            server_list.push(std::ptr::null());
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_SERVER as isize);
+            args.push(server_list.as_ptr() as isize);
+        }
+        if let Some(username) = &self.username {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_USERNAME as isize);
+            args.push(string_arg(username)?);
+        }
+        if let Some(secret) = &self.secret {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_SECRET as isize);
+            args.push(string_arg(secret)?);
+        }
+        if let Some(iface) = &self.iface {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_IFACE as isize);
+            args.push(string_arg(iface)?);
+        }
+        if let Some(name) = &self.name {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_NAME as isize);
+            args.push(string_arg(name)?);
+        }
+        if let Some(label) = &self.label {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_LABEL as isize);
+            args.push(string_arg(label)?);
+        }
+        if let Some(cachemode) = self.cachemode {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_CACHEMODE as isize);
+            args.push(string_arg(cachemode.as_str())?);
+        }
+        if let Some(discard) = self.discard {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_DISCARD as isize);
+            args.push(string_arg(discard.as_str())?);
+        }
+        if let Some(copyonread) = self.copyonread {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_COPYONREAD as isize);
+            args.push(copyonread.into());
+        }
+        if let Some(blocksize) = self.blocksize {
+            args.push(libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_BLOCKSIZE as isize);
+            args.push(blocksize as isize);
+        }
+
+        Ok(AddDriveArgs {
+            filename,
+            args,
+            _strings: strings,
+            _server_list: server_list,
+        })
+    }
+}
+
+/// The arguments of a `guestfs_add_drive_opts` call, see [`DriveOptions::to_args`]
+pub(super) struct AddDriveArgs {
+    filename: CString,
+    args: Vec<isize>,
+    // pointed to by `args`, moving the vectors does not move their contents
+    _strings: Vec<CString>,
+    _server_list: Vec<*const c_char>,
+}
+
+impl AddDriveArgs {
+    /// The path or export name of the drive
+    pub(super) fn filename(&self) -> &OsStr {
+        OsStr::from_bytes(self.filename.to_bytes())
+    }
+
+    /// Call `guestfs_add_drive_opts`, returning `-1` on error
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid libguestfs handle.
+    pub(super) unsafe fn add_to(&self, handle: *mut libguestfs_sys::guestfs_h) -> c_int {
+        let args = &self.args;
+        unsafe {
+            // the limit has to be a literal, keep it in sync with MAX_OPTARGS
+            call_variadic!(
+                26,
+                args,
+                n,
+                args[n],
+                libguestfs_sys::guestfs_add_drive_opts(
+                    handle,
+                    self.filename.as_ptr(),
+                    ...,
+                    -1 as c_int
+                )
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_round_trips() {
+        for (string, server) in [
+            ("localhost", Server::tcp("localhost", None)),
+            ("example.com:10809", Server::tcp("example.com", 10809)),
+            ("192.0.2.1:22", Server::tcp("192.0.2.1", 22)),
+            ("[::1]", Server::tcp("::1", None)),
+            ("[2001:db8::1]:10809", Server::tcp("2001:db8::1", 10809)),
+            ("unix:/tmp/nbd.sock", Server::unix("/tmp/nbd.sock")),
+        ] {
+            assert_eq!(string.parse::<Server>().unwrap(), server, "{string}");
+            assert_eq!(server.to_string(), string);
+        }
+    }
+
+    #[test]
+    fn server_accepts_alternative_spellings() {
+        for (string, server) in [
+            ("tcp:localhost:10809", Server::tcp("localhost", 10809)),
+            ("::1", Server::tcp("::1", None)),
+            ("2001:db8::1", Server::tcp("2001:db8::1", None)),
+        ] {
+            assert_eq!(string.parse::<Server>().unwrap(), server, "{string}");
+        }
+    }
+
+    #[test]
+    fn invalid_servers() {
+        for string in [
+            "",
+            ":10809",
+            "unix:",
+            "host:",
+            "host:port",
+            "host:65536",
+            "[::1",
+            "[::1]10809",
+            "[]:10809",
+        ] {
+            assert!(
+                matches!(string.parse::<Server>(), Err(Error::InvalidDrive(_))),
+                "{string:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn sources_are_validated() {
+        let nbd = Server::tcp("localhost", 10809);
+        let socket = Server::unix("/tmp/nbd.sock");
+        for source in [
+            DriveSource::File("disk.img".into()),
+            DriveSource::nbd(nbd.clone()),
+            DriveSource::nbd_export(socket.clone(), "disk"),
+            DriveSource::remote(Protocol::Gluster, socket.clone(), "volume/image"),
+            DriveSource::Remote {
+                protocol: Protocol::Rbd,
+                servers: Vec::new(),
+                path: "pool/image".into(),
+            },
+            DriveSource::Remote {
+                protocol: Protocol::Rbd,
+                servers: vec![nbd.clone(), Server::tcp("ceph2", None)],
+                path: "pool/image".into(),
+            },
+        ] {
+            assert!(source.validate().is_ok(), "{source:?} was rejected");
+        }
+
+        for source in [
+            DriveSource::nbd(Server::tcp("", 10809)),
+            DriveSource::remote(Protocol::Ssh, socket, "/disk.img"),
+            DriveSource::Remote {
+                protocol: Protocol::Http,
+                servers: Vec::new(),
+                path: "/disk.img".into(),
+            },
+            DriveSource::Remote {
+                protocol: Protocol::Iscsi,
+                servers: vec![nbd.clone(), nbd],
+                path: "iqn.2024-01.com.example:disk/0".into(),
+            },
+        ] {
+            assert!(
+                matches!(source.validate(), Err(Error::InvalidDrive(_))),
+                "{source:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn credentials_need_a_remote_drive() {
+        let options = DriveOptions::new("disk.img", DriveFormat::Raw).username("root");
+        assert!(matches!(options.to_args(), Err(Error::InvalidDrive(_))));
+    }
+
+    #[test]
+    fn optargs_come_in_pairs() {
+        let source = DriveSource::nbd_export(Server::unix("/tmp/nbd.sock"), "disk");
+        let args = DriveOptions::new(source, DriveFormat::Qcow2)
+            .readonly(true)
+            .to_args()
+            .unwrap();
+        assert_eq!(args.filename(), "disk");
+        // readonly, format, protocol and server
+        assert_eq!(args.args.len(), 8);
+        assert_eq!(
+            args.args[0],
+            libguestfs_sys::GUESTFS_ADD_DRIVE_OPTS_READONLY as isize
+        );
+        assert_eq!(args.args[1], 1);
+    }
+
+    #[test]
+    fn secrets_are_not_printed() {
+        let source = DriveSource::remote(Protocol::Rbd, Server::tcp("ceph", None), "pool/disk");
+        let options = DriveOptions::new(source, DriveFormat::Raw)
+            .username("admin")
+            .secret("hunter2");
+        let debug = format!("{options:?}");
+        assert!(!debug.contains("hunter2"), "{debug}");
+        assert!(debug.contains("secret: Some(\"<redacted>\")"), "{debug}");
+        assert!(debug.contains("admin"), "{debug}");
+
+        let debug = format!("{:?}", DriveOptions::new("disk.img", DriveFormat::Raw));
+        assert!(debug.contains("secret: None"), "{debug}");
+    }
+}
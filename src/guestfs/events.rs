@@ -0,0 +1,429 @@
+//! Events emitted by libguestfs, delivered to Rust closures
+//!
+//! Every closure registered with [`GuestFs::on_event`] is boxed, and its address is passed to
+//! libguestfs as the `opaque` pointer of the callback. [`EventHandle`]s may be dropped on any
+//! thread, so they only mark their closure as removed. The thread owning the handle then
+//! deregisters and frees it before its next call, when no callback can be running.
+//!
+//! Closures are identified by an id which is never reused, unlike libguestfs event handles, so
+//! an [`EventHandle`] cannot remove another closure after its own was removed some other way.
+use super::{callbacks::Callbacks, GuestFs, State};
+use crate::Result;
+use libguestfs_sys::guestfs_h;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    ffi::{c_char, c_int, c_void},
+    fmt, ops,
+    ptr::NonNull,
+    slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+};
+
+/// A set of event types, see [`GuestFs::on_event`]
+///
+/// Masks are combined with `|`:
+///
+/// ```
+/// use hvk::guestfs::EventMask;
+///
+/// let logs = EventMask::APPLIANCE | EventMask::LIBRARY | EventMask::WARNING;
+/// assert!(logs.contains(EventMask::LIBRARY));
+/// assert!(!logs.contains(EventMask::PROGRESS));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EventMask(u64);
+
+impl EventMask {
+    /// The handle is being closed, see [`Event::Close`]
+    pub const CLOSE: Self = Self(libguestfs_sys::GUESTFS_EVENT_CLOSE as u64);
+    /// The appliance process exited, see [`Event::SubprocessQuit`]
+    pub const SUBPROCESS_QUIT: Self = Self(libguestfs_sys::GUESTFS_EVENT_SUBPROCESS_QUIT as u64);
+    /// The appliance finished launching, see [`Event::LaunchDone`]
+    pub const LAUNCH_DONE: Self = Self(libguestfs_sys::GUESTFS_EVENT_LAUNCH_DONE as u64);
+    /// A long-running call made progress, see [`Event::Progress`]
+    pub const PROGRESS: Self = Self(libguestfs_sys::GUESTFS_EVENT_PROGRESS as u64);
+    /// Log output of the appliance, see [`Event::Appliance`]
+    pub const APPLIANCE: Self = Self(libguestfs_sys::GUESTFS_EVENT_APPLIANCE as u64);
+    /// Debug messages of the library, see [`Event::Library`]
+    pub const LIBRARY: Self = Self(libguestfs_sys::GUESTFS_EVENT_LIBRARY as u64);
+    /// Trace messages of API calls, see [`Event::Trace`]
+    pub const TRACE: Self = Self(libguestfs_sys::GUESTFS_EVENT_TRACE as u64);
+    /// An API call is entered, see [`Event::Enter`]
+    pub const ENTER: Self = Self(libguestfs_sys::GUESTFS_EVENT_ENTER as u64);
+    /// Warnings of the library, see [`Event::Warning`]
+    pub const WARNING: Self = Self(libguestfs_sys::GUESTFS_EVENT_WARNING as u64);
+    /// Every event type supported by hvk
+    pub const ALL: Self = Self(
+        Self::CLOSE.0
+            | Self::SUBPROCESS_QUIT.0
+            | Self::LAUNCH_DONE.0
+            | Self::PROGRESS.0
+            | Self::APPLIANCE.0
+            | Self::LIBRARY.0
+            | Self::TRACE.0
+            | Self::ENTER.0
+            | Self::WARNING.0,
+    );
+
+    /// The empty set
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// The raw `GUESTFS_EVENT_*` bitmask
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Whether every event type of `other` is also in `self`
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether the set is empty
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl ops::BitOr for EventMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for EventMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// An event emitted by libguestfs, see [`GuestFs::on_event`]
+///
+/// Log messages are passed as raw bytes, since they are not guaranteed to be valid UTF-8
+/// and may be split at arbitrary points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// The handle is being closed
+    ///
+    /// This is emitted from the [`Drop`] of the handle, so a panic of the closure is discarded.
+    Close,
+    /// The appliance process exited
+    SubprocessQuit,
+    /// The appliance finished launching
+    LaunchDone,
+    /// A long-running call made progress
+    ///
+    /// libguestfs sends one event with `position == 0` when the call starts, and one with
+    /// `position == total` when it finishes. Events in between are rate-limited by the appliance.
+    Progress {
+        /// The procedure number of the call in the appliance protocol
+        proc_nr: u64,
+        /// The serial number of the call
+        serial: u64,
+        /// The amount of work done so far, in units of `total`
+        position: u64,
+        /// The total amount of work, in arbitrary units
+        total: u64,
+    },
+    /// Log output of the appliance, only emitted in verbose mode
+    Appliance(&'a [u8]),
+    /// A debug message of the library, only emitted in verbose mode
+    Library(&'a [u8]),
+    /// A trace message of an API call, only emitted in trace mode
+    Trace(&'a [u8]),
+    /// The name of an API call which is being entered
+    Enter(&'a [u8]),
+    /// A warning of the library
+    Warning(&'a [u8]),
+}
+
+impl<'a> Event<'a> {
+    /// Decode the arguments of an event callback, `None` for unknown or malformed events
+    fn from_raw(event: u64, buf: &'a [u8], array: &[u64]) -> Option<Self> {
+        Some(match EventMask(event) {
+            EventMask::CLOSE => Self::Close,
+            EventMask::SUBPROCESS_QUIT => Self::SubprocessQuit,
+            EventMask::LAUNCH_DONE => Self::LaunchDone,
+            EventMask::PROGRESS => match *array {
+                [proc_nr, serial, position, total, ..] => Self::Progress {
+                    proc_nr,
+                    serial,
+                    position,
+                    total,
+                },
+                _ => return None,
+            },
+            EventMask::APPLIANCE => Self::Appliance(buf),
+            EventMask::LIBRARY => Self::Library(buf),
+            EventMask::TRACE => Self::Trace(buf),
+            EventMask::ENTER => Self::Enter(buf),
+            EventMask::WARNING => Self::Warning(buf),
+            _ => return None,
+        })
+    }
+
+    /// The type of the event, as a mask with a single bit set
+    pub fn mask(&self) -> EventMask {
+        match self {
+            Self::Close => EventMask::CLOSE,
+            Self::SubprocessQuit => EventMask::SUBPROCESS_QUIT,
+            Self::LaunchDone => EventMask::LAUNCH_DONE,
+            Self::Progress { .. } => EventMask::PROGRESS,
+            Self::Appliance(_) => EventMask::APPLIANCE,
+            Self::Library(_) => EventMask::LIBRARY,
+            Self::Trace(_) => EventMask::TRACE,
+            Self::Enter(_) => EventMask::ENTER,
+            Self::Warning(_) => EventMask::WARNING,
+        }
+    }
+}
+
+type EventHandler = Box<dyn FnMut(&Event<'_>) + Send>;
+
+/// A registered closure, passed to libguestfs as the `opaque` pointer of the callback
+struct Slot {
+    // only dereferenced during a callback, while the handle and its callbacks are alive
+    callbacks: NonNull<Callbacks>,
+    handler: RefCell<EventHandler>,
+    // set when its `EventHandle` is dropped, possibly on another thread
+    removed: AtomicBool,
+}
+
+/// A closure registered with libguestfs
+struct Registration {
+    event_handle: c_int,
+    slot: NonNull<Slot>,
+}
+
+/// The closures registered with a handle, shared with their [`EventHandle`]s
+pub(super) struct EventRegistry {
+    // the closures registered with libguestfs, by id
+    registrations: BTreeMap<u64, Registration>,
+    // closures whose `EventHandle` was dropped, to be deregistered by the owner of the handle
+    removed: Vec<u64>,
+    next_id: u64,
+}
+
+// Safety: slots are only freed by the thread owning the handle, while the registry is locked,
+// and other threads only touch their `removed` flag
+unsafe impl Send for EventRegistry {}
+
+impl EventRegistry {
+    pub(super) fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            registrations: BTreeMap::new(),
+            removed: Vec::new(),
+            next_id: 0,
+        }))
+    }
+
+    fn lock(registry: &Mutex<Self>) -> MutexGuard<'_, Self> {
+        registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Release every closure, once the handle is closed and cannot call them anymore
+    pub(super) fn close(registry: &Mutex<Self>) {
+        let mut registry = Self::lock(registry);
+        registry.removed.clear();
+        for registration in std::mem::take(&mut registry.registrations).into_values() {
+            drop(unsafe { Box::from_raw(registration.slot.as_ptr()) });
+        }
+    }
+}
+
+unsafe extern "C" fn event_callback(
+    _g: *mut guestfs_h,
+    opaque: *mut c_void,
+    event: u64,
+    _event_handle: c_int,
+    _flags: c_int,
+    buf: *const c_char,
+    buf_len: usize,
+    array: *const u64,
+    array_len: usize,
+) {
+    // Safety: slots are only freed by the thread owning the handle outside of any call,
+    // or after the handle is closed
+    let slot = unsafe { &*opaque.cast::<Slot>() };
+    if slot.removed.load(Ordering::Acquire) {
+        return;
+    }
+    let callbacks = unsafe { slot.callbacks.as_ref() };
+
+    let buf = if buf.is_null() {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(buf.cast::<u8>(), buf_len) }
+    };
+    let array = if array.is_null() {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(array, array_len) }
+    };
+    let Some(event) = Event::from_raw(event, buf, array) else {
+        return;
+    };
+
+    callbacks.catch(|| {
+        // a closure which somehow triggers its own event is not called recursively
+        if let Ok(mut handler) = slot.handler.try_borrow_mut() {
+            handler(&event);
+        }
+    });
+}
+
+/// A closure registered with [`GuestFs::on_event`], which is removed when this is dropped
+///
+/// The handle may be sent to and dropped on another thread than the one using the [`GuestFs`].
+#[must_use = "the closure is removed as soon as the handle is dropped"]
+pub struct EventHandle {
+    registry: Arc<Mutex<EventRegistry>>,
+    id: u64,
+}
+
+impl fmt::Debug for EventHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EventHandle").field(&self.id).finish()
+    }
+}
+
+impl EventHandle {
+    /// Keep the closure registered until the handle is closed
+    #[cfg(feature = "tracing")]
+    pub(super) fn detach(self) {
+        let this = std::mem::ManuallyDrop::new(self);
+        // Safety: `this` is never used or dropped again
+        drop(unsafe { std::ptr::read(&this.registry) });
+    }
+}
+
+impl Drop for EventHandle {
+    fn drop(&mut self) {
+        // the handle may be in use on another thread, so only its owner may deregister the closure
+        let mut registry = EventRegistry::lock(&self.registry);
+        if let Some(registration) = registry.registrations.get(&self.id) {
+            unsafe { registration.slot.as_ref() }
+                .removed
+                .store(true, Ordering::Release);
+            registry.removed.push(self.id);
+        }
+    }
+}
+
+impl<S: State> GuestFs<'_, S> {
+    /// Call `handler` with every event of a type in `mask`, until the returned handle is dropped
+    ///
+    /// The closure is called on the thread making the libguestfs call which emits the event.
+    /// If it panics, the panic is resumed once that call returns. The handle may outlive
+    /// this [`GuestFs`]. Once it is dropped the closure is not called anymore, and it is
+    /// released on the next call or when the [`GuestFs`] is dropped.
+    ///
+    /// ```ignore
+    /// use hvk::guestfs::{Event, EventMask};
+    ///
+    /// let _progress = g.on_event(EventMask::PROGRESS, |event| {
+    ///     if let Event::Progress { position, total, .. } = event {
+    ///         eprintln!("{position}/{total}");
+    ///     }
+    /// })?;
+    /// g.download("/big.img", "big.img")?;
+    /// ```
+    #[doc(alias = "guestfs_set_event_callback")]
+    pub fn on_event(
+        &self,
+        mask: EventMask,
+        handler: impl FnMut(&Event<'_>) + Send + 'static,
+    ) -> Result<EventHandle> {
+        let slot = NonNull::from(Box::leak(Box::new(Slot {
+            callbacks: self.callbacks,
+            handler: RefCell::new(Box::new(handler)),
+            removed: AtomicBool::new(false),
+        })));
+        let event_handle = self.call("set_event_callback", &[], || {
+            let event_handle = unsafe {
+                libguestfs_sys::guestfs_set_event_callback(
+                    self.handle,
+                    Some(event_callback),
+                    mask.bits(),
+                    0,
+                    slot.as_ptr().cast(),
+                )
+            };
+            (event_handle != -1).then_some(event_handle)
+        });
+        let event_handle = match event_handle {
+            Ok(event_handle) => event_handle,
+            Err(error) => {
+                drop(unsafe { Box::from_raw(slot.as_ptr()) });
+                return Err(error);
+            }
+        };
+
+        let registry = Arc::clone(&self.callbacks().events);
+        let id = {
+            let mut registry = EventRegistry::lock(&registry);
+            let id = registry.next_id;
+            registry.next_id += 1;
+            registry
+                .registrations
+                .insert(id, Registration { event_handle, slot });
+            id
+        };
+        Ok(EventHandle { registry, id })
+    }
+
+    /// Deregister the closures whose [`EventHandle`] was dropped
+    ///
+    /// This runs before every call, on the thread owning the handle, so no callback is running.
+    pub(super) fn remove_dropped_handlers(&self) {
+        let mut registry = EventRegistry::lock(&self.callbacks().events);
+        for id in std::mem::take(&mut registry.removed) {
+            if let Some(registration) = registry.registrations.remove(&id) {
+                self.deregister(registration);
+            }
+        }
+    }
+
+    /// The ids of the closures registered so far, see
+    /// [`remove_handlers_except`](Self::remove_handlers_except)
+    pub(super) fn handler_ids(&self) -> BTreeSet<u64> {
+        let registry = EventRegistry::lock(&self.callbacks().events);
+        registry.registrations.keys().copied().collect()
+    }
+
+    /// Deregister every closure but the ones in `keep`, even if their [`EventHandle`] is alive
+    ///
+    /// Like [`remove_dropped_handlers`](Self::remove_dropped_handlers), this must only run on
+    /// the thread owning the handle, outside of any call. The removed [`EventHandle`]s do
+    /// nothing when they are dropped.
+    pub(super) fn remove_handlers_except(&self, keep: &BTreeSet<u64>) {
+        self.remove_dropped_handlers();
+        let mut registry = EventRegistry::lock(&self.callbacks().events);
+        let removed: Vec<u64> = registry
+            .registrations
+            .keys()
+            .filter(|id| !keep.contains(id))
+            .copied()
+            .collect();
+        for id in removed {
+            if let Some(registration) = registry.registrations.remove(&id) {
+                self.deregister(registration);
+            }
+        }
+    }
+
+    fn deregister(&self, registration: Registration) {
+        unsafe {
+            libguestfs_sys::guestfs_delete_event_callback(self.handle, registration.event_handle);
+            drop(Box::from_raw(registration.slot.as_ptr()));
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Inspection of the operating systems installed on the disks
+//!
+//! See <https://libguestfs.org/guestfs.3.html#inspection>. The `inspect_get_*` functions take
+//! one of the roots returned by [`GuestFs::inspect_os`], and mostly return `"unknown"` when
+//! something could not be determined rather than failing.
+use super::{
+    types::ApplicationList, Device, GuestFs, GuestString, GuestStringList, Launched, Mountable,
+};
+use crate::Result;
+use libguestfs_sys::guestfs_h;
+use std::{
+    collections::BTreeMap,
+    ffi::{c_char, c_int, CString, OsStr},
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    ptr::NonNull,
+};
+
+type StringGetter = unsafe extern "C" fn(*mut guestfs_h, *const c_char) -> *mut c_char;
+type IntGetter = unsafe extern "C" fn(*mut guestfs_h, *const c_char) -> c_int;
+type ListGetter = unsafe extern "C" fn(*mut guestfs_h, *const c_char) -> *mut *mut c_char;
+
+impl GuestFs<'_, Launched> {
+    fn inspect_string(
+        &self,
+        api: &'static str,
+        root: &Mountable,
+        getter: StringGetter,
+    ) -> Result<String> {
+        let root = root.to_string();
+        let c_root = CString::new(root.as_str())?;
+        let value = self.call(api, &[&root.as_str()], || unsafe {
+            GuestString::from_raw(getter(self.handle, c_root.as_ptr()))
+        })?;
+        Ok(value.to_string_lossy().into_owned())
+    }
+
+    fn inspect_int(&self, api: &'static str, root: &Mountable, getter: IntGetter) -> Result<i32> {
+        let root = root.to_string();
+        let c_root = CString::new(root.as_str())?;
+        self.call(api, &[&root.as_str()], || {
+            let value = unsafe { getter(self.handle, c_root.as_ptr()) };
+            (value != -1).then_some(value)
+        })
+    }
+
+    fn inspect_list(
+        &self,
+        api: &'static str,
+        root: &Mountable,
+        getter: ListGetter,
+    ) -> Result<GuestStringList> {
+        let root = root.to_string();
+        let c_root = CString::new(root.as_str())?;
+        self.call(api, &[&root.as_str()], || unsafe {
+            GuestStringList::from_raw(getter(self.handle, c_root.as_ptr()))
+        })
+    }
+
+    /// Inspect the disks for operating systems, returning the root device of each one found
+    ///
+    /// This mounts and unmounts filesystems, so it is best called before mounting anything.
+    pub fn inspect_os(&mut self) -> Result<Box<[Mountable]>> {
+        let roots = self.call("inspect_os", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_inspect_os(self.handle))
+        })?;
+        Ok(roots.map_lossy(Mountable::from_guestfs))
+    }
+
+    /// The roots found by the last [`inspect_os`](Self::inspect_os)
+    pub fn inspect_get_roots(&self) -> Result<Box<[Mountable]>> {
+        let roots = self.call("inspect_get_roots", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_inspect_get_roots(self.handle))
+        })?;
+        Ok(roots.map_lossy(Mountable::from_guestfs))
+    }
+
+    /// The type of the operating system, e.g. `linux` or `windows`
+    pub fn inspect_get_type(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_type",
+            root,
+            libguestfs_sys::guestfs_inspect_get_type,
+        )
+    }
+
+    /// The distribution of the operating system, e.g. `fedora` or `ubuntu`
+    pub fn inspect_get_distro(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_distro",
+            root,
+            libguestfs_sys::guestfs_inspect_get_distro,
+        )
+    }
+
+    /// The major version of the operating system, `0` if unknown
+    pub fn inspect_get_major_version(&self, root: &Mountable) -> Result<i32> {
+        self.inspect_int(
+            "inspect_get_major_version",
+            root,
+            libguestfs_sys::guestfs_inspect_get_major_version,
+        )
+    }
+
+    /// The minor version of the operating system, `0` if unknown
+    pub fn inspect_get_minor_version(&self, root: &Mountable) -> Result<i32> {
+        self.inspect_int(
+            "inspect_get_minor_version",
+            root,
+            libguestfs_sys::guestfs_inspect_get_minor_version,
+        )
+    }
+
+    /// The product name of the operating system, e.g. `Fedora release 39 (Thirty Nine)`
+    pub fn inspect_get_product_name(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_product_name",
+            root,
+            libguestfs_sys::guestfs_inspect_get_product_name,
+        )
+    }
+
+    /// The product variant of the operating system, e.g. `Server` or `Client` on Windows
+    pub fn inspect_get_product_variant(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_product_variant",
+            root,
+            libguestfs_sys::guestfs_inspect_get_product_variant,
+        )
+    }
+
+    /// The architecture of the operating system, e.g. `x86_64`
+    pub fn inspect_get_arch(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_arch",
+            root,
+            libguestfs_sys::guestfs_inspect_get_arch,
+        )
+    }
+
+    /// The hostname configured in the operating system
+    pub fn inspect_get_hostname(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_hostname",
+            root,
+            libguestfs_sys::guestfs_inspect_get_hostname,
+        )
+    }
+
+    /// The package format of the operating system, e.g. `rpm` or `deb`
+    pub fn inspect_get_package_format(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_package_format",
+            root,
+            libguestfs_sys::guestfs_inspect_get_package_format,
+        )
+    }
+
+    /// The package manager of the operating system, e.g. `dnf` or `apt`
+    pub fn inspect_get_package_management(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_package_management",
+            root,
+            libguestfs_sys::guestfs_inspect_get_package_management,
+        )
+    }
+
+    /// The libosinfo short ID of the operating system, e.g. `fedora39`
+    pub fn inspect_get_osinfo(&self, root: &Mountable) -> Result<String> {
+        self.inspect_string(
+            "inspect_get_osinfo",
+            root,
+            libguestfs_sys::guestfs_inspect_get_osinfo,
+        )
+    }
+
+    /// The Windows drive letters of the operating system, mapped to their devices
+    ///
+    /// This is empty for other operating systems.
+    pub fn inspect_get_drive_mappings(&self, root: &Mountable) -> Result<BTreeMap<String, Device>> {
+        let mappings = self.inspect_list(
+            "inspect_get_drive_mappings",
+            root,
+            libguestfs_sys::guestfs_inspect_get_drive_mappings,
+        )?;
+        Ok(mappings
+            .pairs()
+            .map(|(letter, device)| {
+                (
+                    letter.to_string_lossy().into_owned(),
+                    Device::from_guestfs(device.to_string_lossy().into_owned()),
+                )
+            })
+            .collect())
+    }
+
+    /// The mountpoints of the operating system, e.g. from `/etc/fstab`, mapped to their devices
+    pub fn inspect_get_mountpoints(
+        &self,
+        root: &Mountable,
+    ) -> Result<BTreeMap<PathBuf, Mountable>> {
+        let mountpoints = self.inspect_list(
+            "inspect_get_mountpoints",
+            root,
+            libguestfs_sys::guestfs_inspect_get_mountpoints,
+        )?;
+        Ok(mountpoints
+            .pairs()
+            .map(|(mountpoint, device)| {
+                (
+                    OsStr::from_bytes(mountpoint.to_bytes()).into(),
+                    Mountable::from_guestfs(device.to_string_lossy().into_owned()),
+                )
+            })
+            .collect())
+    }
+
+    /// The filesystems which belong to the operating system
+    pub fn inspect_get_filesystems(&self, root: &Mountable) -> Result<Box<[Mountable]>> {
+        let filesystems = self.inspect_list(
+            "inspect_get_filesystems",
+            root,
+            libguestfs_sys::guestfs_inspect_get_filesystems,
+        )?;
+        Ok(filesystems.map_lossy(Mountable::from_guestfs))
+    }
+
+    /// The applications installed in the operating system
+    ///
+    /// The filesystems of the operating system must be mounted, e.g. as returned by
+    /// [`inspect_get_mountpoints`](Self::inspect_get_mountpoints).
+    pub fn inspect_list_applications2(&self, root: &Mountable) -> Result<ApplicationList> {
+        let root = root.to_string();
+        let c_root = CString::new(root.as_str())?;
+        self.call("inspect_list_applications2", &[&root.as_str()], || {
+            NonNull::new(unsafe {
+                libguestfs_sys::guestfs_inspect_list_applications2(self.handle, c_root.as_ptr())
+            })
+            .map(|applications| unsafe { ApplicationList::from_raw(applications.as_ptr()) })
+        })
+    }
+}
@@ -1,30 +1,47 @@
-use crate::Result;
+use crate::{
+    error::{Error, GuestFsError},
+    Result,
+};
+use callbacks::Callbacks;
+use ffi_utils::path_to_cstring;
 use libguestfs_sys::guestfs_h;
 use std::{
-    borrow::Borrow,
-    ffi::{CStr, CString},
-    io::{Cursor, Read},
-    path::Path,
+    collections::BTreeMap,
+    ffi::{c_int, CStr, CString, OsStr, OsString},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    ptr::NonNull,
 };
-use types::DirEntList;
+use trace::CallSpan;
+use types::{DirEntList, LvmLvList, LvmPvList, LvmVgList, StatNs, StatNsList};
+pub mod buf;
+mod builder;
+mod callbacks;
+mod cancel;
+pub mod device;
+pub mod drive;
+mod events;
 mod ffi_utils;
-mod types;
-
-#[must_use]
-fn null_terminated_array_to_vec<'a>(array: *mut *mut i8) -> Vec<&'a str> {
-    let mut vec = Vec::new();
-    let mut i = 0;
-    loop {
-        let ptr = unsafe { *array.offset(i) };
-        if ptr.is_null() {
-            break;
-        }
-        let cstr = unsafe { CStr::from_ptr(ptr) };
-        vec.push(cstr.to_str().unwrap());
-        i += 1;
-    }
-    vec
-}
+mod inspect;
+mod pool;
+mod progress;
+pub mod state;
+mod trace;
+mod transfer;
+pub mod types;
+
+pub use buf::{GuestBuf, GuestString, GuestStringList};
+pub use builder::GuestFsBuilder;
+pub use callbacks::SilenceErrors;
+pub use cancel::CancelHandle;
+pub use device::{Device, LogicalVolume, Mountable, Partition};
+pub use drive::{CacheMode, Discard, DriveFormat, DriveOptions, DriveSource, Protocol, Server};
+pub use events::{Event, EventHandle, EventMask};
+pub use pool::{GuestFsPool, Lease};
+#[cfg(feature = "indicatif")]
+pub use progress::progress_bar;
+pub use progress::{Progress, WithProgress};
+pub use state::{Configuring, Launched, State};
 
 // guestfs functions return 0 on success, -1 on error
 
@@ -32,277 +49,1101 @@ fn null_terminated_array_to_vec<'a>(array: *mut *mut i8) -> Vec<&'a str> {
 // Main GuestFS struct and its associated functions
 
 /// GuestFS struct that wraps the libguestfs handle
-pub struct GuestFs<'a> {
+///
+/// The `S` parameter tracks the lifecycle phase of the handle, see [`state`] for details.
+/// A new handle starts out as [`Configuring`] and becomes [`Launched`] after [`GuestFs::launch`].
+///
+/// # Threads
+///
+/// libguestfs allows a handle to be used from any thread, but only from one thread at a time.
+/// So a `GuestFs` is [`Send`] and can be moved to a worker thread, but it is not [`Sync`], and
+/// borrowed handles such as files cannot be shared between threads. Closures registered with
+/// the handle run on the thread making the call, which is why they must be [`Send`].
+/// The only handles meant to be used from other threads are [`EventHandle`] and
+/// [`CancelHandle`]. See [`GuestFsPool`] to run many appliances in parallel.
+pub struct GuestFs<'a, S: State = Launched> {
     handle: *mut guestfs_h,
-    _marker: std::marker::PhantomData<&'a ()>,
+    callbacks: NonNull<Callbacks>,
+    _marker: std::marker::PhantomData<(&'a (), S)>,
 }
 
-impl Drop for GuestFs<'_> {
+// Safety: libguestfs handles may be used from any thread as long as calls do not overlap,
+// which holds since `GuestFs` is not `Sync` and every call borrows it. Callbacks only run on
+// the calling thread, and the state shared with other threads is synchronized.
+unsafe impl<S: State> Send for GuestFs<'_, S> {}
+
+impl<S: State> Drop for GuestFs<'_, S> {
     fn drop(&mut self) {
         unsafe {
-            // unconditionally shutdown and ignore all errors
-            // todo: log errors
-
-            let _ = self.shutdown();
+            // unconditionally shutdown, errors cannot be returned from here so they are only logged
+            let retcode = libguestfs_sys::guestfs_shutdown(self.handle);
+            #[cfg(feature = "tracing")]
+            if retcode == -1 {
+                tracing::warn!(error = %self.error_message(), "failed to shut down the appliance");
+            }
+            #[cfg(not(feature = "tracing"))]
+            let _ = retcode;
 
+            self.callbacks().cancel.close();
             libguestfs_sys::guestfs_close(self.handle);
+            // the callbacks may be called until the handle is closed
+            Callbacks::free(self.callbacks);
         }
     }
 }
 
-impl From<*mut guestfs_h> for GuestFs<'_> {
-    fn from(handle: *mut guestfs_h) -> Self {
-        Self {
+impl GuestFs<'_, Configuring> {
+    /// Take ownership of a libguestfs handle created outside of hvk
+    ///
+    /// The handle is closed when the returned [`GuestFs`] is dropped. It always starts out as
+    /// [`Configuring`], so a handle which was already launched cannot be wrapped.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid handle returned by `guestfs_create` or `guestfs_create_flags`
+    /// which has not been launched, and is not closed, used or owned by anything else afterwards.
+    pub unsafe fn from_raw(handle: *mut guestfs_h) -> Self {
+        let g = Self {
             handle,
+            callbacks: Callbacks::install(handle),
             _marker: std::marker::PhantomData,
-        }
+        };
+        #[cfg(feature = "tracing")]
+        trace::forward_logs(&g);
+        g
     }
 }
 
-// todo: take from filesystem.rs because i ported it
-impl<'a> GuestFs<'a> {
-    pub(crate) fn handle(&self) -> *mut guestfs_h {
+// Functions available in every phase
+impl<S: State> GuestFs<'_, S> {
+    /// Returns the underlying libguestfs handle
+    ///
+    /// This can be used to call into libguestfs functions that are not wrapped by hvk yet.
+    /// The handle is still owned by this [`GuestFs`] and must not be closed.
+    pub fn handle(&self) -> *mut guestfs_h {
         self.handle
     }
 
-    #[doc(alias = "create", "guestfs_create")]
-    pub fn new() -> Self {
-        let handle = unsafe { libguestfs_sys::guestfs_create() };
-        Self {
-            handle,
-            _marker: std::marker::PhantomData,
+    fn callbacks(&self) -> &Callbacks {
+        // Safety: the callbacks are only freed when the handle is dropped
+        unsafe { self.callbacks.as_ref() }
+    }
+
+    /// Build the error of the libguestfs call `api` that just failed
+    ///
+    /// `args` are the guest paths and devices the call was made with, to give the error some context.
+    fn last_error(&self, api: &'static str, args: &[&dyn AsRef<OsStr>]) -> Error {
+        // a panic of the error handler takes precedence over the error
+        self.callbacks().resume_panic();
+
+        let errno = unsafe { libguestfs_sys::guestfs_last_errno(self.handle) };
+        let error = Error::GuestFs(GuestFsError::new(
+            api,
+            args.iter().map(|arg| arg.as_ref().to_os_string()).collect(),
+            self.error_message(),
+            errno,
+        ));
+        self.callbacks().cancel.classify(error)
+    }
+
+    /// The message of the last error on the handle
+    fn error_message(&self) -> String {
+        let message = unsafe { libguestfs_sys::guestfs_last_error(self.handle) };
+        if message.is_null() {
+            "unknown error".into()
+        } else {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
         }
     }
 
-    #[must_use]
-    #[inline]
-    fn parse_error(&self, retcode: i32) -> crate::error::Error {
-        let cstr = unsafe { CStr::from_ptr(libguestfs_sys::guestfs_last_error(self.handle)) };
-        crate::error::Error::GuestFsError(format!(
-            "returned {}: {}",
-            retcode,
-            cstr.to_string_lossy()
-        ))
+    /// Make the libguestfs call `api`, where `f` returns `None` on error
+    ///
+    /// Every call goes through here, so panics of callbacks run during the call are resumed
+    /// as soon as it returns, whether it failed or not.
+    fn call<T>(
+        &self,
+        api: &'static str,
+        args: &[&dyn AsRef<OsStr>],
+        f: impl FnOnce() -> Option<T>,
+    ) -> Result<T> {
+        let span = CallSpan::enter(api, args);
+        self.remove_dropped_handlers();
+        self.callbacks().cancel.reset();
+        let result = f();
+        self.callbacks().resume_panic();
+        let result = result.ok_or_else(|| self.last_error(api, args));
+        span.finish(&result);
+        result
     }
 
-    #[must_use]
+    /// Make the libguestfs call `api`, where `f` returns `-1` on error
     #[inline]
-    fn last_error_number(&self) -> i32 {
-        unsafe { libguestfs_sys::guestfs_last_errno(self.handle) }
+    fn check(
+        &self,
+        api: &'static str,
+        args: &[&dyn AsRef<OsStr>],
+        f: impl FnOnce() -> c_int,
+    ) -> Result<()> {
+        self.call(api, args, || (f() != -1).then_some(()))
     }
+}
 
-    #[must_use]
-    #[inline]
-    fn wrap_error(&self, retcode: i32) -> Result<()> {
-        if retcode != 0 {
-            Err(self.parse_error(retcode))
-        } else {
-            Ok(())
-        }
+// Functions that configure the appliance before it is launched
+impl<'a> GuestFs<'a, Configuring> {
+    /// Create a new handle with the default settings
+    ///
+    /// This is a shorthand for `GuestFs::builder().build()`.
+    /// Unlike `guestfs_create`, environment variables such as `LIBGUESTFS_BACKEND` are ignored,
+    /// see [`GuestFsBuilder::parse_environment`] to opt in.
+    #[doc(alias("create", "guestfs_create"))]
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    /// Start configuring a new handle
+    pub fn builder() -> GuestFsBuilder {
+        GuestFsBuilder::default()
+    }
+
+    /// Apply the `LIBGUESTFS_*` environment variables to the handle
+    #[doc(alias = "guestfs_parse_environment")]
+    pub fn parse_environment(&mut self) -> Result<()> {
+        self.check("parse_environment", &[], || unsafe {
+            libguestfs_sys::guestfs_parse_environment(self.handle)
+        })
+    }
+
+    /// Set the backend used to run the appliance, e.g. `direct` or `libvirt`
+    #[doc(alias = "guestfs_set_backend")]
+    pub fn set_backend(&mut self, backend: &str) -> Result<()> {
+        let c_backend = CString::new(backend)?;
+        self.check("set_backend", &[], || unsafe {
+            libguestfs_sys::guestfs_set_backend(self.handle, c_backend.as_ptr())
+        })
+    }
+
+    /// Set the memory size of the appliance, in megabytes
+    #[doc(alias = "guestfs_set_memsize")]
+    pub fn set_memsize(&mut self, memsize: i32) -> Result<()> {
+        self.check("set_memsize", &[], || unsafe {
+            libguestfs_sys::guestfs_set_memsize(self.handle, memsize)
+        })
+    }
+
+    /// Set the number of virtual CPUs of the appliance
+    #[doc(alias = "guestfs_set_smp")]
+    pub fn set_smp(&mut self, smp: i32) -> Result<()> {
+        self.check("set_smp", &[], || unsafe {
+            libguestfs_sys::guestfs_set_smp(self.handle, smp)
+        })
+    }
+
+    /// Set the hypervisor binary used to run the appliance
+    #[doc(alias = "guestfs_set_hv")]
+    pub fn set_hv(&mut self, hv: &Path) -> Result<()> {
+        let c_hv = ffi_utils::path_to_cstring(hv)?;
+        self.check("set_hv", &[], || unsafe {
+            libguestfs_sys::guestfs_set_hv(self.handle, c_hv.as_ptr())
+        })
+    }
+
+    /// Append extra options to the appliance kernel command line
+    #[doc(alias = "guestfs_set_append")]
+    pub fn set_append(&mut self, append: &str) -> Result<()> {
+        let c_append = CString::new(append)?;
+        self.check("set_append", &[], || unsafe {
+            libguestfs_sys::guestfs_set_append(self.handle, c_append.as_ptr())
+        })
+    }
+
+    /// Enable or disable network access from the appliance
+    #[doc(alias = "guestfs_set_network")]
+    pub fn set_network(&mut self, network: bool) -> Result<()> {
+        self.check("set_network", &[], || unsafe {
+            libguestfs_sys::guestfs_set_network(self.handle, network.into())
+        })
+    }
+
+    /// Enable or disable syncing and unmounting the filesystems when the handle is closed
+    #[doc(alias = "guestfs_set_autosync")]
+    pub fn set_autosync(&mut self, autosync: bool) -> Result<()> {
+        self.check("set_autosync", &[], || unsafe {
+            libguestfs_sys::guestfs_set_autosync(self.handle, autosync.into())
+        })
+    }
+
+    /// Enable or disable the recovery process, which kills the appliance if the program exits unexpectedly
+    #[doc(alias = "guestfs_set_recovery_proc")]
+    pub fn set_recovery_proc(&mut self, recovery_proc: bool) -> Result<()> {
+        self.check("set_recovery_proc", &[], || unsafe {
+            libguestfs_sys::guestfs_set_recovery_proc(self.handle, recovery_proc.into())
+        })
+    }
+
+    /// Set the directory used to cache the appliance
+    #[doc(alias = "guestfs_set_cachedir")]
+    pub fn set_cachedir(&mut self, cachedir: &Path) -> Result<()> {
+        let c_cachedir = ffi_utils::path_to_cstring(cachedir)?;
+        self.check("set_cachedir", &[], || unsafe {
+            libguestfs_sys::guestfs_set_cachedir(self.handle, c_cachedir.as_ptr())
+        })
+    }
+
+    /// Set the directory used for temporary files
+    #[doc(alias = "guestfs_set_tmpdir")]
+    pub fn set_tmpdir(&mut self, tmpdir: &Path) -> Result<()> {
+        let c_tmpdir = ffi_utils::path_to_cstring(tmpdir)?;
+        self.check("set_tmpdir", &[], || unsafe {
+            libguestfs_sys::guestfs_set_tmpdir(self.handle, c_tmpdir.as_ptr())
+        })
+    }
+
+    /// Enable or disable verbose messages
+    #[doc(alias = "guestfs_set_verbose")]
+    pub fn set_verbose(&mut self, verbose: bool) -> Result<()> {
+        self.check("set_verbose", &[], || unsafe {
+            libguestfs_sys::guestfs_set_verbose(self.handle, verbose.into())
+        })
+    }
+
+    /// Enable or disable command traces
+    #[doc(alias = "guestfs_set_trace")]
+    pub fn set_trace(&mut self, trace: bool) -> Result<()> {
+        self.check("set_trace", &[], || unsafe {
+            libguestfs_sys::guestfs_set_trace(self.handle, trace.into())
+        })
     }
 
     /// Adds a new drive
-    pub fn add_drive(&mut self, path: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_add_drive(self.handle, CString::new(path)?.as_ptr())
+    pub fn add_drive(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("add_drive", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_add_drive(self.handle, c_path.as_ptr())
+        })
+    }
+
+    /// Adds a new drive with the given options
+    #[doc(alias = "guestfs_add_drive_opts")]
+    pub fn add_drive_opts(&mut self, options: &DriveOptions) -> Result<()> {
+        let args = options.to_args()?;
+        self.check("add_drive_opts", &[&args.filename()], || unsafe {
+            args.add_to(self.handle)
+        })
+    }
+
+    /// Adds a new drive in read-only mode
+    pub fn add_drive_ro(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("add_drive_ro", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_add_drive_ro(self.handle, c_path.as_ptr())
+        })
+    }
+
+    /// Adds a temporary, empty drive of `size` bytes, which is deleted when the handle is closed
+    pub fn add_drive_scratch(&mut self, size: i64) -> Result<()> {
+        // no optional arguments, just the terminator
+        self.check("add_drive_scratch", &[], || unsafe {
+            libguestfs_sys::guestfs_add_drive_scratch(self.handle, size, -1 as c_int)
+        })
+    }
+
+    /// Launch the libguestfs appliance
+    ///
+    /// This consumes the configuring handle and returns a [`Launched`] one,
+    /// which can be used to mount and access the drives added so far.
+    /// If launching fails, the handle is closed.
+    #[doc(alias = "guestfs_launch")]
+    pub fn launch(self) -> Result<GuestFs<'a, Launched>> {
+        self.check("launch", &[], || unsafe {
+            libguestfs_sys::guestfs_launch(self.handle)
+        })?;
+        // hand the libguestfs handle over to the launched GuestFs without closing it
+        let this = std::mem::ManuallyDrop::new(self);
+        Ok(GuestFs {
+            handle: this.handle,
+            callbacks: this.callbacks,
+            _marker: std::marker::PhantomData,
         })
     }
+}
+
+// todo: take from filesystem.rs because i ported it
+impl GuestFs<'_, Launched> {
     /// Shutdown the libguestfs appliance
     pub fn shutdown(&self) -> Result<()> {
-        self.wrap_error(unsafe { libguestfs_sys::guestfs_shutdown(self.handle) })
+        self.check("shutdown", &[], || unsafe {
+            libguestfs_sys::guestfs_shutdown(self.handle)
+        })
     }
 
-    pub fn mount(&mut self, devpath: &str, mountpoint: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_mount(
-                self.handle,
-                CString::new(devpath)?.as_ptr(),
-                CString::new(mountpoint)?.as_ptr(),
-            )
+    /// Get the process ID of the appliance
+    ///
+    /// This only works with the `direct` backend.
+    pub fn get_pid(&self) -> Result<i32> {
+        self.call("get_pid", &[], || {
+            let pid = unsafe { libguestfs_sys::guestfs_get_pid(self.handle) };
+            (pid != -1).then_some(pid)
         })
     }
 
+    /// Mount a device or btrfs subvolume on a mountpoint
+    pub fn mount(
+        &mut self,
+        mountable: impl Into<Mountable>,
+        mountpoint: impl AsRef<Path>,
+    ) -> Result<()> {
+        let devpath = mountable.into().to_string();
+        let c_devpath = CString::new(devpath.as_str())?;
+        let c_mountpoint = path_to_cstring(mountpoint.as_ref())?;
+        self.check(
+            "mount",
+            &[&devpath.as_str(), &mountpoint.as_ref()],
+            || unsafe {
+                libguestfs_sys::guestfs_mount(
+                    self.handle,
+                    c_devpath.as_ptr(),
+                    c_mountpoint.as_ptr(),
+                )
+            },
+        )
+    }
+
+    /// Mount a device or btrfs subvolume read-only on a mountpoint
+    pub fn mount_ro(
+        &mut self,
+        mountable: impl Into<Mountable>,
+        mountpoint: impl AsRef<Path>,
+    ) -> Result<()> {
+        let devpath = mountable.into().to_string();
+        let c_devpath = CString::new(devpath.as_str())?;
+        let c_mountpoint = path_to_cstring(mountpoint.as_ref())?;
+        self.check(
+            "mount_ro",
+            &[&devpath.as_str(), &mountpoint.as_ref()],
+            || unsafe {
+                libguestfs_sys::guestfs_mount_ro(
+                    self.handle,
+                    c_devpath.as_ptr(),
+                    c_mountpoint.as_ptr(),
+                )
+            },
+        )
+    }
+
+    /// Mount a device or btrfs subvolume on a mountpoint with the mount options `options`, e.g. `ro,noexec`
+    pub fn mount_options(
+        &mut self,
+        options: &str,
+        mountable: impl Into<Mountable>,
+        mountpoint: impl AsRef<Path>,
+    ) -> Result<()> {
+        let devpath = mountable.into().to_string();
+        let c_options = CString::new(options)?;
+        let c_devpath = CString::new(devpath.as_str())?;
+        let c_mountpoint = path_to_cstring(mountpoint.as_ref())?;
+        self.check(
+            "mount_options",
+            &[&options, &devpath.as_str(), &mountpoint.as_ref()],
+            || unsafe {
+                libguestfs_sys::guestfs_mount_options(
+                    self.handle,
+                    c_options.as_ptr(),
+                    c_devpath.as_ptr(),
+                    c_mountpoint.as_ptr(),
+                )
+            },
+        )
+    }
+
+    /// Mount a device or btrfs subvolume on a mountpoint as a filesystem of type `vfstype`, with the mount
+    /// options `options`
+    pub fn mount_vfs(
+        &mut self,
+        options: &str,
+        vfstype: &str,
+        mountable: impl Into<Mountable>,
+        mountpoint: impl AsRef<Path>,
+    ) -> Result<()> {
+        let devpath = mountable.into().to_string();
+        let c_options = CString::new(options)?;
+        let c_vfstype = CString::new(vfstype)?;
+        let c_devpath = CString::new(devpath.as_str())?;
+        let c_mountpoint = path_to_cstring(mountpoint.as_ref())?;
+        self.check(
+            "mount_vfs",
+            &[&options, &vfstype, &devpath.as_str(), &mountpoint.as_ref()],
+            || unsafe {
+                libguestfs_sys::guestfs_mount_vfs(
+                    self.handle,
+                    c_options.as_ptr(),
+                    c_vfstype.as_ptr(),
+                    c_devpath.as_ptr(),
+                    c_mountpoint.as_ptr(),
+                )
+            },
+        )
+    }
+
+    /// The devices of the mounted filesystems
+    pub fn mounts(&self) -> Result<Box<[Mountable]>> {
+        let devices = self.call("mounts", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_mounts(self.handle))
+        })?;
+        Ok(devices.map_lossy(Mountable::from_guestfs))
+    }
+
+    /// The mounted filesystems, as a map of their mountpoints to their devices
+    pub fn mountpoints(&self) -> Result<BTreeMap<PathBuf, Mountable>> {
+        let mountpoints = self.call("mountpoints", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_mountpoints(self.handle))
+        })?;
+        // the list alternates devices and mountpoints
+        Ok(mountpoints
+            .pairs()
+            .map(|(device, mountpoint)| {
+                (
+                    OsStr::from_bytes(mountpoint.to_bytes()).into(),
+                    Mountable::from_guestfs(device.to_string_lossy().into_owned()),
+                )
+            })
+            .collect())
+    }
+
     /// Unmount a device from a mountpoint
-    pub fn umount(&mut self, mountpoint: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_umount(self.handle, CString::new(mountpoint)?.as_ptr())
+    pub fn umount(&mut self, mountpoint: impl AsRef<Path>) -> Result<()> {
+        let c_mountpoint = path_to_cstring(mountpoint.as_ref())?;
+        self.check("umount", &[&mountpoint.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_umount(self.handle, c_mountpoint.as_ptr())
+        })
+    }
+
+    /// Unmount every mounted filesystem
+    pub fn umount_all(&mut self) -> Result<()> {
+        self.check("umount_all", &[], || unsafe {
+            libguestfs_sys::guestfs_umount_all(self.handle)
         })
     }
     /// Creates an empty file at the specified path
-    pub fn touch(&mut self, path: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_touch(self.handle, CString::new(path)?.as_ptr())
+    pub fn touch(&self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("touch", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_touch(self.handle, c_path.as_ptr())
+        })
+    }
+
+    /// Create a filesystem of type `fstype` (e.g. `ext4`) on a device or partition
+    pub fn mkfs(&self, fstype: &str, device: impl AsRef<Device>) -> Result<()> {
+        let device = device.as_ref().as_str();
+        let c_fstype = CString::new(fstype)?;
+        let c_device = CString::new(device)?;
+        self.check("mkfs", &[&device], || unsafe {
+            libguestfs_sys::guestfs_mkfs(self.handle, c_fstype.as_ptr(), c_device.as_ptr())
         })
     }
 
+    /// Create a single partition covering the whole disk, with a partition table of type
+    /// `parttype` (e.g. `mbr` or `gpt`)
+    pub fn part_disk(&self, device: impl AsRef<Device>, parttype: &str) -> Result<()> {
+        let device = device.as_ref().as_str();
+        let c_device = CString::new(device)?;
+        let c_parttype = CString::new(parttype)?;
+        self.check("part_disk", &[&device, &parttype], || unsafe {
+            libguestfs_sys::guestfs_part_disk(self.handle, c_device.as_ptr(), c_parttype.as_ptr())
+        })
+    }
+
+    /// List the block devices, i.e. the disks, e.g. /dev/sda
+    pub fn list_devices(&self) -> Result<Box<[Device]>> {
+        let devices = self.call("list_devices", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_list_devices(self.handle))
+        })?;
+        Ok(devices.map_lossy(Device::from_guestfs))
+    }
+
     /// List partitions inside the disk image
     ///
-    /// Returns a list of device paths of the partitions, e.g. /dev/sda1
-    pub fn list_partitions(&self) -> Result<Box<[String]>> {
-        match unsafe { libguestfs_sys::guestfs_list_partitions(self.handle) } {
-            partitions if partitions.is_null() => Err(self.parse_error(self.last_error_number())),
-            partitions => {
-                let partitions = unsafe { ffi_utils::from_raw_cstring_array_full(partitions) };
-                Ok(partitions
-                    .into_iter()
-                    .map(|x| x.to_string_lossy().into_owned())
-                    .collect())
-            }
-        }
+    /// Returns a list of device paths of the partitions, e.g. /dev/sda1. A name which is not a
+    /// partition name is an [`Error::InvalidDevice`].
+    pub fn list_partitions(&self) -> Result<Box<[Partition]>> {
+        let partitions = self.call("list_partitions", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_list_partitions(self.handle))
+        })?;
+        partitions.try_map_lossy(Partition::from_guestfs)
+    }
+
+    /// List the LVM logical volumes, e.g. /dev/vg0/root
+    pub fn lvs(&self) -> Result<Box<[LogicalVolume]>> {
+        let volumes = self.call("lvs", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_lvs(self.handle))
+        })?;
+        volumes.try_map_lossy(LogicalVolume::from_guestfs)
+    }
+
+    /// List the LVM logical volumes with all their details
+    pub fn lvs_full(&self) -> Result<LvmLvList> {
+        self.call("lvs_full", &[], || {
+            NonNull::new(unsafe { libguestfs_sys::guestfs_lvs_full(self.handle) })
+                .map(|list| unsafe { LvmLvList::from_raw(list.as_ptr()) })
+        })
+    }
+
+    /// List the LVM volume groups with all their details
+    pub fn vgs_full(&self) -> Result<LvmVgList> {
+        self.call("vgs_full", &[], || {
+            NonNull::new(unsafe { libguestfs_sys::guestfs_vgs_full(self.handle) })
+                .map(|list| unsafe { LvmVgList::from_raw(list.as_ptr()) })
+        })
+    }
+
+    /// List the LVM physical volumes with all their details
+    pub fn pvs_full(&self) -> Result<LvmPvList> {
+        self.call("pvs_full", &[], || {
+            NonNull::new(unsafe { libguestfs_sys::guestfs_pvs_full(self.handle) })
+                .map(|list| unsafe { LvmPvList::from_raw(list.as_ptr()) })
+        })
     }
 
     /// List filesystems inside the disk image
     ///
-    pub fn list_filesystems(&self) -> Result<Box<[String]>> {
-        match unsafe { libguestfs_sys::guestfs_list_filesystems(self.handle) } {
-            filesystems if filesystems.is_null() => Err(self.parse_error(self.last_error_number())),
-            filesystems => {
-                let filesystems = unsafe { ffi_utils::from_raw_cstring_array_full(filesystems) };
-                Ok(filesystems
-                    .into_iter()
-                    .map(|x| x.to_string_lossy().into_owned())
-                    .collect())
-            }
-        }
+    /// Returns a map of the filesystems to their types, e.g. `ext4`. The type is `unknown`
+    /// if it could not be detected, and `swap` for swap partitions.
+    pub fn list_filesystems(&self) -> Result<BTreeMap<Mountable, String>> {
+        let filesystems = self.call("list_filesystems", &[], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_list_filesystems(self.handle))
+        })?;
+        Ok(filesystems
+            .pairs()
+            .map(|(mountable, fstype)| {
+                (
+                    Mountable::from_guestfs(mountable.to_string_lossy().into_owned()),
+                    fstype.to_string_lossy().into_owned(),
+                )
+            })
+            .collect())
     }
 
-    /// Concatenate a file and return its contents as an array of bytes
-    pub fn cat(&self, path: &str) -> Result<Box<[u8]>> {
-        // // let mut size: u64 = 0;
-        // let data = unsafe {
-        //     libguestfs_sys::guestfs_cat(self.handle, CString::new(path)?.as_ptr())
-        // };
-        // if data.is_null() {
-        //     return Err(self.parse_error(self.last_error_number()));
-        // }
-
-        // // Convert to a Vec<u8>
-        // // todo: am i doing this right?
-        // let vec = unsafe { std::slice::from_raw_parts(data, 0) };
-
-        // Ok(vec.into_iter().map(|x| *x as u8).collect())
-        match unsafe { libguestfs_sys::guestfs_cat(self.handle, CString::new(path)?.as_ptr()) } {
-            data if data.is_null() => Err(self.parse_error(self.last_error_number())),
-            data => {
-                let data = ffi_utils::from_raw_array_full(data);
-                Ok(data.into_iter().map(|x| x.to_owned() as u8).collect())
-            }
-        }
+    /// The disk a partition is on, e.g. `/dev/sda` for `/dev/sda1`
+    pub fn part_to_dev(&self, partition: &Partition) -> Result<Device> {
+        let c_partition = CString::new(partition.as_str())?;
+        let device = self.call("part_to_dev", &[&partition.as_str()], || unsafe {
+            GuestString::from_raw(libguestfs_sys::guestfs_part_to_dev(
+                self.handle,
+                c_partition.as_ptr(),
+            ))
+        })?;
+        Ok(Device::from_guestfs(device.to_string_lossy().into_owned()))
     }
 
-    /// Read a file and return a pointer to a Read trait object
+    /// The number of a partition on its disk, e.g. `1` for `/dev/sda1`
+    pub fn part_to_partnum(&self, partition: &Partition) -> Result<i32> {
+        let c_partition = CString::new(partition.as_str())?;
+        self.call("part_to_partnum", &[&partition.as_str()], || {
+            let partnum = unsafe {
+                libguestfs_sys::guestfs_part_to_partnum(self.handle, c_partition.as_ptr())
+            };
+            (partnum != -1).then_some(partnum)
+        })
+    }
+
+    /// The canonical name of a device, e.g. `/dev/sda1` for `/dev/vda1`, or `/dev/vg0/root`
+    /// for `/dev/mapper/vg0-root`
+    pub fn canonical_device_name(&self, device: impl AsRef<Device>) -> Result<Device> {
+        let device = device.as_ref().as_str();
+        let c_device = CString::new(device)?;
+        let canonical = self.call("canonical_device_name", &[&device], || unsafe {
+            GuestString::from_raw(libguestfs_sys::guestfs_canonical_device_name(
+                self.handle,
+                c_device.as_ptr(),
+            ))
+        })?;
+        Ok(Device::from_guestfs(
+            canonical.to_string_lossy().into_owned(),
+        ))
+    }
+
+    /// Concatenate a file and return its contents
     ///
-    /// This function is different from [`Self::cat`] in that it can correctly handle files that
-    /// contain embedded ASCII NUL characters, and it returns a `Cursor<&[i8]>` instead of a `Vec<u8>`.
-    pub fn read_file(&self, path: &str, buf_size: &mut usize) -> Result<Cursor<&[i8]>> {
-        let buf = unsafe {
-            libguestfs_sys::guestfs_read_file(self.handle, CString::new(path)?.as_ptr(), buf_size)
-        };
+    /// The contents end at the first NUL byte, use [`Self::read_file`] for binary files.
+    pub fn cat(&self, path: impl AsRef<Path>) -> Result<GuestBuf> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("cat", &[&path.as_ref()], || unsafe {
+            GuestBuf::from_raw_cstr(libguestfs_sys::guestfs_cat(self.handle, c_path.as_ptr()))
+        })
+    }
 
-        if buf.is_null() {
-            Err(self.parse_error(self.last_error_number()))
-        } else {
-            Ok(Cursor::new(unsafe {
-                std::slice::from_raw_parts(buf, *buf_size)
-            }))
-        }
+    /// Read the whole contents of a file
+    ///
+    /// Unlike [`Self::cat`], this correctly handles files that contain embedded NUL bytes.
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Result<GuestBuf> {
+        let mut size = 0;
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("read_file", &[&path.as_ref()], || unsafe {
+            GuestBuf::from_raw(
+                libguestfs_sys::guestfs_read_file(self.handle, c_path.as_ptr(), &mut size),
+                size,
+            )
+        })
     }
 
     /// Read a file and return lines as an iterator
-    pub fn read_lines(&self, path: &str) -> Result<Box<[String]>> {
-        match unsafe {
-            libguestfs_sys::guestfs_read_lines(self.handle, CString::new(path)?.as_ptr())
-        } {
-            lines if lines.is_null() => Err(self.parse_error(self.last_error_number())),
-            lines => {
-                let lines = unsafe { ffi_utils::from_raw_cstring_array_full(lines) };
-                Ok(lines
-                    .into_iter()
-                    .map(|x| x.to_string_lossy().into_owned())
-                    .collect())
-            }
-        }
+    pub fn read_lines(&self, path: impl AsRef<Path>) -> Result<Box<[String]>> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        let lines = self.call("read_lines", &[&path.as_ref()], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_read_lines(
+                self.handle,
+                c_path.as_ptr(),
+            ))
+        })?;
+        Ok(lines.to_strings_lossy())
     }
 
     /// Create a directory at the specified path
-    pub fn mkdir(&self, path: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_mkdir(self.handle, CString::new(path)?.as_ptr())
+    pub fn mkdir(&self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("mkdir", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_mkdir(self.handle, c_path.as_ptr())
         })
     }
 
     /// Create a symbolic link to a specified target in the filesystem
-    pub fn ln_s(&self, target: &str, linkpath: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_ln_s(
+    pub fn ln_s(&self, target: impl AsRef<Path>, linkpath: impl AsRef<Path>) -> Result<()> {
+        let c_target = path_to_cstring(target.as_ref())?;
+        let c_linkpath = path_to_cstring(linkpath.as_ref())?;
+        self.check("ln_s", &[&target.as_ref(), &linkpath.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_ln_s(self.handle, c_target.as_ptr(), c_linkpath.as_ptr())
+        })
+    }
+
+    /// Create a directory and any missing parent directories
+    pub fn mkdir_p(&self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("mkdir_p", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_mkdir_p(self.handle, c_path.as_ptr())
+        })
+    }
+
+    /// Remove a single file
+    pub fn rm(&self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("rm", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_rm(self.handle, c_path.as_ptr())
+        })
+    }
+
+    /// Remove a single, empty directory
+    pub fn rmdir(&self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("rmdir", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_rmdir(self.handle, c_path.as_ptr())
+        })
+    }
+
+    /// Remove a file or directory recursively, like `rm -rf`
+    pub fn rm_rf(&self, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("rm_rf", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_rm_rf(self.handle, c_path.as_ptr())
+        })
+    }
+
+    /// Move or rename a file or directory
+    pub fn mv(&self, src: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        let c_src = path_to_cstring(src.as_ref())?;
+        let c_dest = path_to_cstring(dest.as_ref())?;
+        self.check("mv", &[&src.as_ref(), &dest.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_mv(self.handle, c_src.as_ptr(), c_dest.as_ptr())
+        })
+    }
+
+    /// Copy a file (`cp`), into `dest` if it is a directory
+    pub fn cp(&self, src: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        let c_src = path_to_cstring(src.as_ref())?;
+        let c_dest = path_to_cstring(dest.as_ref())?;
+        self.check("cp", &[&src.as_ref(), &dest.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_cp(self.handle, c_src.as_ptr(), c_dest.as_ptr())
+        })
+    }
+
+    /// Copy a file or directory recursively, preserving all attributes (`cp -a`)
+    pub fn cp_a(&self, src: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        let c_src = path_to_cstring(src.as_ref())?;
+        let c_dest = path_to_cstring(dest.as_ref())?;
+        self.check("cp_a", &[&src.as_ref(), &dest.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_cp_a(self.handle, c_src.as_ptr(), c_dest.as_ptr())
+        })
+    }
+
+    /// Create a hard link to a specified target in the filesystem
+    pub fn ln(&self, target: impl AsRef<Path>, linkname: impl AsRef<Path>) -> Result<()> {
+        let c_target = path_to_cstring(target.as_ref())?;
+        let c_linkname = path_to_cstring(linkname.as_ref())?;
+        self.check("ln", &[&target.as_ref(), &linkname.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_ln(self.handle, c_target.as_ptr(), c_linkname.as_ptr())
+        })
+    }
+
+    /// Read the target of a symbolic link
+    pub fn readlink(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        let target = self.call("readlink", &[&path.as_ref()], || unsafe {
+            GuestString::from_raw(libguestfs_sys::guestfs_readlink(
+                self.handle,
+                c_path.as_ptr(),
+            ))
+        })?;
+        Ok(target.to_os_string().into())
+    }
+
+    /// Return the canonical, absolute path with all symbolic links resolved
+    pub fn realpath(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        let resolved = self.call("realpath", &[&path.as_ref()], || unsafe {
+            GuestString::from_raw(libguestfs_sys::guestfs_realpath(
+                self.handle,
+                c_path.as_ptr(),
+            ))
+        })?;
+        Ok(resolved.to_os_string().into())
+    }
+
+    /// Check whether a file or directory exists, following symbolic links
+    pub fn exists(&self, path: impl AsRef<Path>) -> Result<bool> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("exists", &[&path.as_ref()], || {
+            let exists = unsafe { libguestfs_sys::guestfs_exists(self.handle, c_path.as_ptr()) };
+            (exists != -1).then_some(exists != 0)
+        })
+    }
+
+    /// Change the mode (permission bits) of a file or directory
+    pub fn chmod(&self, mode: i32, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("chmod", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_chmod(self.handle, mode, c_path.as_ptr())
+        })
+    }
+
+    /// Get file information, following symbolic links
+    pub fn statns(&self, path: impl AsRef<Path>) -> Result<StatNs> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("statns", &[&path.as_ref()], || {
+            NonNull::new(unsafe { libguestfs_sys::guestfs_statns(self.handle, c_path.as_ptr()) })
+                .map(|stat| unsafe { StatNs::from_raw(stat.as_ptr()) })
+        })
+    }
+
+    /// Get file information, without following symbolic links
+    pub fn lstatns(&self, path: impl AsRef<Path>) -> Result<StatNs> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("lstatns", &[&path.as_ref()], || {
+            NonNull::new(unsafe { libguestfs_sys::guestfs_lstatns(self.handle, c_path.as_ptr()) })
+                .map(|stat| unsafe { StatNs::from_raw(stat.as_ptr()) })
+        })
+    }
+
+    /// Get file information for many files in a directory at once, without following symbolic links
+    ///
+    /// The result has one entry per name. Entries of names that could not be looked up
+    /// have their `st_ino` set to `-1`.
+    pub fn lstatnslist<N: AsRef<Path>>(
+        &self,
+        path: impl AsRef<Path>,
+        names: &[N],
+    ) -> Result<StatNsList> {
+        let names = names
+            .iter()
+            .map(|name| path_to_cstring(name.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut name_ptrs: Vec<*const std::os::raw::c_char> =
+            names.iter().map(|name| name.as_ptr()).collect();
+        name_ptrs.push(std::ptr::null());
+
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("lstatnslist", &[&path.as_ref()], || {
+            NonNull::new(unsafe {
+                libguestfs_sys::guestfs_lstatnslist(
+                    self.handle,
+                    c_path.as_ptr(),
+                    name_ptrs.as_ptr(),
+                )
+            })
+            .map(|list| unsafe { StatNsList::from_raw(list.as_ptr()) })
+        })
+    }
+
+    /// Get the size of a file in bytes
+    pub fn filesize(&self, path: impl AsRef<Path>) -> Result<i64> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("filesize", &[&path.as_ref()], || {
+            let size = unsafe { libguestfs_sys::guestfs_filesize(self.handle, c_path.as_ptr()) };
+            (size != -1).then_some(size)
+        })
+    }
+
+    /// Read up to `count` bytes from a file, starting at `offset`
+    ///
+    /// This may return fewer bytes than requested, and an empty buffer at the end of the file.
+    pub fn pread(&self, path: impl AsRef<Path>, count: i32, offset: i64) -> Result<GuestBuf> {
+        let mut size = 0;
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("pread", &[&path.as_ref()], || unsafe {
+            GuestBuf::from_raw(
+                libguestfs_sys::guestfs_pread(
+                    self.handle,
+                    c_path.as_ptr(),
+                    count,
+                    offset,
+                    &mut size,
+                ),
+                size,
+            )
+        })
+    }
+
+    /// Write `content` to a file at `offset`, returning the number of bytes written
+    ///
+    /// This may write fewer bytes than requested.
+    pub fn pwrite(&self, path: impl AsRef<Path>, content: &[u8], offset: i64) -> Result<usize> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("pwrite", &[&path.as_ref()], || {
+            let written = unsafe {
+                libguestfs_sys::guestfs_pwrite(
+                    self.handle,
+                    c_path.as_ptr(),
+                    content.as_ptr().cast(),
+                    content.len(),
+                    offset,
+                )
+            };
+            (written != -1).then_some(written as usize)
+        })
+    }
+
+    /// Change the size of a file, extending it with zeroes or truncating it
+    pub fn truncate_size(&self, path: impl AsRef<Path>, size: i64) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("truncate_size", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_truncate_size(self.handle, c_path.as_ptr(), size)
+        })
+    }
+
+    /// Sync the disks, writing all cached data to the drives
+    pub fn sync(&self) -> Result<()> {
+        self.check("sync", &[], || unsafe {
+            libguestfs_sys::guestfs_sync(self.handle)
+        })
+    }
+
+    /// Create a file with the given content, replacing it if it already exists
+    pub fn write(&self, path: impl AsRef<Path>, content: &[u8]) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("write", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_write(
                 self.handle,
-                CString::new(target)?.as_ptr(),
-                CString::new(linkpath)?.as_ptr(),
+                c_path.as_ptr(),
+                content.as_ptr().cast(),
+                content.len(),
             )
         })
     }
 
     /// List subdirectories of a directory
-    pub fn readdir(&self, path: &str) -> Result<DirEntList> {
-        match unsafe { libguestfs_sys::guestfs_readdir(self.handle, CString::new(path)?.as_ptr()) }
-        {
-            entries if entries.is_null() => Err(self.parse_error(self.last_error_number())),
-            entries => Ok(DirEntList { inner: entries }),
-        }
+    pub fn readdir(&self, path: impl AsRef<Path>) -> Result<DirEntList> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.call("readdir", &[&path.as_ref()], || {
+            NonNull::new(unsafe { libguestfs_sys::guestfs_readdir(self.handle, c_path.as_ptr()) })
+                .map(|entries| unsafe { DirEntList::from_raw(entries.as_ptr()) })
+        })
+    }
+
+    /// List the names of the files in a directory, without `.` and `..`
+    pub fn ls(&self, directory: impl AsRef<Path>) -> Result<Vec<OsString>> {
+        let c_directory = path_to_cstring(directory.as_ref())?;
+        let names = self.call("ls", &[&directory.as_ref()], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_ls(
+                self.handle,
+                c_directory.as_ptr(),
+            ))
+        })?;
+        Ok(names.to_os_strings())
+    }
+
+    /// Recursively list all files and directories below a directory
+    ///
+    /// The paths are relative to `directory`, and sorted.
+    pub fn find(&self, directory: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let c_directory = path_to_cstring(directory.as_ref())?;
+        let paths = self.call("find", &[&directory.as_ref()], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_find(
+                self.handle,
+                c_directory.as_ptr(),
+            ))
+        })?;
+        Ok(paths
+            .to_os_strings()
+            .into_iter()
+            .map(PathBuf::from)
+            .collect())
     }
 
     /// Download a file from the disk image to the host
-    pub fn download(&self, path: &str, dest: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_download(
+    pub fn download(&self, path: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        let c_dest = path_to_cstring(dest.as_ref())?;
+        self.check("download", &[&path.as_ref(), &dest.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_download(self.handle, c_path.as_ptr(), c_dest.as_ptr())
+        })
+    }
+
+    /// Download `size` bytes of a file from the disk image to the host, starting at `offset`
+    pub fn download_offset(
+        &self,
+        path: impl AsRef<Path>,
+        dest: impl AsRef<Path>,
+        offset: i64,
+        size: i64,
+    ) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        let c_dest = path_to_cstring(dest.as_ref())?;
+        self.check(
+            "download_offset",
+            &[&path.as_ref(), &dest.as_ref()],
+            || unsafe {
+                libguestfs_sys::guestfs_download_offset(
+                    self.handle,
+                    c_path.as_ptr(),
+                    c_dest.as_ptr(),
+                    offset,
+                    size,
+                )
+            },
+        )
+    }
+
+    /// Upload a file from the host to the disk image, replacing it if it already exists
+    pub fn upload(&self, src: impl AsRef<Path>, path: impl AsRef<Path>) -> Result<()> {
+        let c_src = path_to_cstring(src.as_ref())?;
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("upload", &[&src.as_ref(), &path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_upload(self.handle, c_src.as_ptr(), c_path.as_ptr())
+        })
+    }
+
+    /// Upload a file from the host into an existing file of the disk image, starting at `offset`
+    ///
+    /// The file is not truncated, bytes past the end of the upload are left untouched.
+    pub fn upload_offset(
+        &self,
+        src: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+        offset: i64,
+    ) -> Result<()> {
+        let c_src = path_to_cstring(src.as_ref())?;
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check(
+            "upload_offset",
+            &[&src.as_ref(), &path.as_ref()],
+            || unsafe {
+                libguestfs_sys::guestfs_upload_offset(
+                    self.handle,
+                    c_src.as_ptr(),
+                    c_path.as_ptr(),
+                    offset,
+                )
+            },
+        )
+    }
+
+    /// Pack a directory of the disk image into an uncompressed tarball on the host
+    pub fn tar_out(&self, directory: impl AsRef<Path>, tarfile: impl AsRef<Path>) -> Result<()> {
+        let c_directory = path_to_cstring(directory.as_ref())?;
+        let c_tarfile = path_to_cstring(tarfile.as_ref())?;
+        self.check(
+            "tar_out",
+            &[&directory.as_ref(), &tarfile.as_ref()],
+            || unsafe {
+                libguestfs_sys::guestfs_tar_out(
+                    self.handle,
+                    c_directory.as_ptr(),
+                    c_tarfile.as_ptr(),
+                )
+            },
+        )
+    }
+
+    /// Copy the whole contents of the device `src` to the device `dest`
+    pub fn copy_device_to_device(
+        &self,
+        src: impl AsRef<Device>,
+        dest: impl AsRef<Device>,
+    ) -> Result<()> {
+        let src = src.as_ref().as_str();
+        let dest = dest.as_ref().as_str();
+        let c_src = CString::new(src)?;
+        let c_dest = CString::new(dest)?;
+        // no optional arguments, just the terminator
+        self.check("copy_device_to_device", &[&src, &dest], || unsafe {
+            libguestfs_sys::guestfs_copy_device_to_device(
                 self.handle,
-                CString::new(path)?.as_ptr(),
-                CString::new(dest)?.as_ptr(),
+                c_src.as_ptr(),
+                c_dest.as_ptr(),
+                -1 as c_int,
             )
         })
     }
+
+    /// Create a file of `len` bytes, all set to `c`, replacing it if it already exists
+    pub fn fill(&self, c: u8, len: i32, path: impl AsRef<Path>) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        self.check("fill", &[&path.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_fill(self.handle, c.into(), len, c_path.as_ptr())
+        })
+    }
+
+    /// Overwrite the whole device with zeroes, skipping blocks which are already zero
+    pub fn zero_device(&self, device: impl AsRef<Device>) -> Result<()> {
+        let device = device.as_ref().as_str();
+        let c_device = CString::new(device)?;
+        self.check("zero_device", &[&device], || unsafe {
+            libguestfs_sys::guestfs_zero_device(self.handle, c_device.as_ptr())
+        })
+    }
+
     /// Get ACL type of a file
     /// The acltype parameter may be:
-    /// access
-    ///     Return the ordinary (access) ACL for any file, directory or other filesystem object.
-    /// default
-    ///
-    ///     Return the default ACL. Normally this only makes sense if path is a directory.
+    /// - `access`: Return the ordinary (access) ACL for any file, directory or other filesystem object.
+    /// - `default`: Return the default ACL. Normally this only makes sense if path is a directory.
     ///
     // todo: high level function: enum for acltype
-    pub fn acl_get_file(&self, path: &str, acltype: &str) -> Result<String> {
-        match unsafe {
-            libguestfs_sys::guestfs_acl_get_file(
+    pub fn acl_get_file(&self, path: impl AsRef<Path>, acltype: &str) -> Result<String> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        let c_acltype = CString::new(acltype)?;
+        let acl = self.call("acl_get_file", &[&path.as_ref()], || unsafe {
+            GuestString::from_raw(libguestfs_sys::guestfs_acl_get_file(
                 self.handle,
-                CString::new(path)?.as_ptr(),
-                CString::new(acltype)?.as_ptr(),
-            )
-        } {
-            acl if acl.is_null() => Err(self.parse_error(self.last_error_number())),
-            acl => {
-                let acl = unsafe { CString::from_raw(acl) };
-                Ok(acl.to_string_lossy().into_owned())
-            }
-        }
+                c_path.as_ptr(),
+                c_acltype.as_ptr(),
+            ))
+        })?;
+        Ok(acl.to_string_lossy().into_owned())
     }
 
-    pub fn acl_set_file(&self, path: &str, acltype: &str, acl: &str) -> Result<()> {
-        self.wrap_error(unsafe {
+    pub fn acl_set_file(&self, path: impl AsRef<Path>, acltype: &str, acl: &str) -> Result<()> {
+        let c_path = path_to_cstring(path.as_ref())?;
+        let c_acltype = CString::new(acltype)?;
+        let c_acl = CString::new(acl)?;
+        self.check("acl_set_file", &[&path.as_ref()], || unsafe {
             libguestfs_sys::guestfs_acl_set_file(
                 self.handle,
-                CString::new(path)?.as_ptr(),
-                CString::new(acltype)?.as_ptr(),
-                CString::new(acl)?.as_ptr(),
+                c_path.as_ptr(),
+                c_acltype.as_ptr(),
+                c_acl.as_ptr(),
             )
         })
     }
 
-    pub fn add_drive_ro(&self, path: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_add_drive_ro(self.handle, CString::new(path)?.as_ptr())
-        })
-    }
-
     // This function accepts a list of domain arguments which are variable args
     // time to use varargs
     // variyak time
@@ -312,16 +1153,67 @@ impl<'a> GuestFs<'a> {
     //     })
     // }
     //
+    /// Create a new Augeas handle for editing configuration files
+    pub fn aug_init(&self, root: impl AsRef<Path>, flags: i32) -> Result<()> {
+        let c_root = path_to_cstring(root.as_ref())?;
+        self.check("aug_init", &[&root.as_ref()], || unsafe {
+            libguestfs_sys::guestfs_aug_init(self.handle, c_root.as_ptr(), flags)
+        })
+    }
+
+    /// Look up the value of an Augeas path
+    pub fn aug_get(&self, augpath: &str) -> Result<String> {
+        let c_augpath = CString::new(augpath)?;
+        let value = self.call("aug_get", &[&augpath], || unsafe {
+            GuestString::from_raw(libguestfs_sys::guestfs_aug_get(
+                self.handle,
+                c_augpath.as_ptr(),
+            ))
+        })?;
+        Ok(value.to_string_lossy().into_owned())
+    }
+
+    /// Set the value of an Augeas path
+    pub fn aug_set(&self, augpath: &str, val: &str) -> Result<()> {
+        let c_augpath = CString::new(augpath)?;
+        let c_val = CString::new(val)?;
+        self.check("aug_set", &[&augpath], || unsafe {
+            libguestfs_sys::guestfs_aug_set(self.handle, c_augpath.as_ptr(), c_val.as_ptr())
+        })
+    }
+
+    /// Return the Augeas nodes which match an Augeas path expression
+    pub fn aug_match(&self, augpath: &str) -> Result<Box<[String]>> {
+        let c_augpath = CString::new(augpath)?;
+        let matches = self.call("aug_match", &[&augpath], || unsafe {
+            GuestStringList::from_raw(libguestfs_sys::guestfs_aug_match(
+                self.handle,
+                c_augpath.as_ptr(),
+            ))
+        })?;
+        Ok(matches.to_strings_lossy())
+    }
+
+    /// Write all pending Augeas changes to disk
+    pub fn aug_save(&self) -> Result<()> {
+        self.check("aug_save", &[], || unsafe {
+            libguestfs_sys::guestfs_aug_save(self.handle)
+        })
+    }
+
     /// Clear the Augeas node (similar to `augtool clear`)
-    pub fn aug_clear(&mut self, augpath: &str) -> Result<()> {
-        self.wrap_error(unsafe {
-            libguestfs_sys::guestfs_aug_clear(self.handle, CString::new(augpath)?.as_ptr())
+    pub fn aug_clear(&self, augpath: &str) -> Result<()> {
+        let c_augpath = CString::new(augpath)?;
+        self.check("aug_clear", &[&augpath], || unsafe {
+            libguestfs_sys::guestfs_aug_clear(self.handle, c_augpath.as_ptr())
         })
     }
 
     /// Close the Augeas handle
     /// This function should be called automatically in the idiomatic interface, but we're exposing it here so we can use it
-    pub fn aug_close(&mut self) -> Result<()> {
-        self.wrap_error(unsafe { libguestfs_sys::guestfs_aug_close(self.handle) })
+    pub fn aug_close(&self) -> Result<()> {
+        self.check("aug_close", &[], || unsafe {
+            libguestfs_sys::guestfs_aug_close(self.handle)
+        })
     }
 }
@@ -0,0 +1,266 @@
+//! A pool of pre-launched appliances
+//!
+//! Launching an appliance takes a couple of seconds, much longer than most inspections.
+//! [`GuestFsPool`] launches its appliances up front, in parallel, and lends them out to
+//! worker threads with [`GuestFsPool::lease`].
+use super::{DriveOptions, GuestFs, GuestFsBuilder, Launched};
+use crate::{error::Error, Result};
+use std::{
+    collections::BTreeSet,
+    fmt, io,
+    ops::{Deref, DerefMut},
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+    thread,
+};
+
+struct PoolState {
+    idle: Vec<GuestFs<'static, Launched>>,
+    // idle and leased appliances, plus the ones being launched
+    live: usize,
+}
+
+/// A thread-safe pool of appliances, all launched against the same read-only drives
+///
+/// Leases are returned to the pool when they are dropped, with every filesystem unmounted, and
+/// the error handler and the event closures registered during the lease removed.
+/// Any other state, e.g. an open Augeas session, is kept.
+/// Appliances which cannot be reset are closed, and replaced on demand.
+///
+/// The format of every drive has to be given, since the images scanned by a pool are usually
+/// untrusted, see [`DriveFormat`](super::DriveFormat).
+///
+/// ```ignore
+/// use hvk::guestfs::{DriveFormat, DriveOptions, GuestFsPool};
+///
+/// let drives = ["disk1.qcow2", "disk2.qcow2"]
+///     .map(|path| DriveOptions::new(path, DriveFormat::Qcow2));
+/// let pool = GuestFsPool::new(8, drives)?;
+/// std::thread::scope(|scope| {
+///     for _ in 0..8 {
+///         scope.spawn(|| {
+///             let g = pool.lease()?;
+///             g.list_filesystems()
+///         });
+///     }
+/// });
+/// ```
+pub struct GuestFsPool {
+    builder: GuestFsBuilder,
+    drives: Vec<DriveOptions>,
+    size: usize,
+    state: Mutex<PoolState>,
+    // notified whenever an appliance is returned or closed
+    returned: Condvar,
+}
+
+impl GuestFsPool {
+    /// Launch `size` appliances with the default settings, each with every one of `drives`
+    /// attached read-only
+    pub fn new(size: usize, drives: impl IntoIterator<Item = DriveOptions>) -> Result<Self> {
+        Self::with_builder(GuestFs::builder(), size, drives)
+    }
+
+    /// Launch `size` appliances configured by `builder`, each with every one of `drives`
+    /// attached read-only
+    ///
+    /// The drives are always added read-only, whatever their options say. The appliances are
+    /// launched in parallel. If any of them fails to launch, the others
+    /// are closed and the first error is returned.
+    pub fn with_builder(
+        builder: GuestFsBuilder,
+        size: usize,
+        drives: impl IntoIterator<Item = DriveOptions>,
+    ) -> Result<Self> {
+        if size == 0 {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a pool needs at least one appliance",
+            )));
+        }
+
+        let mut pool = Self {
+            builder,
+            drives: drives
+                .into_iter()
+                .map(|drive| drive.readonly(true))
+                .collect(),
+            size,
+            state: Mutex::new(PoolState {
+                idle: Vec::with_capacity(size),
+                live: size,
+            }),
+            returned: Condvar::new(),
+        };
+        let idle = thread::scope(|scope| {
+            let pool = &pool;
+            let launches: Vec<_> = (0..size).map(|_| scope.spawn(|| pool.launch())).collect();
+            launches
+                .into_iter()
+                .map(|launch| {
+                    launch
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+        pool.state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .idle = idle;
+        Ok(pool)
+    }
+
+    fn launch(&self) -> Result<GuestFs<'static, Launched>> {
+        let mut g = self.builder.clone().build()?;
+        for drive in &self.drives {
+            g.add_drive_opts(drive)?;
+        }
+        g.launch()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The number of appliances of the pool
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of appliances ready to be leased right away
+    pub fn idle(&self) -> usize {
+        self.lock().idle.len()
+    }
+
+    /// Lease an appliance, waiting until one is returned if they are all in use
+    ///
+    /// If an appliance was closed because it could not be reset, a new one is launched
+    /// in its place, which fails if it cannot be launched.
+    pub fn lease(&self) -> Result<Lease<'_>> {
+        let mut state = self.lock();
+        loop {
+            if let Some(fs) = state.idle.pop() {
+                return Ok(Lease::new(self, fs));
+            }
+            if state.live < self.size {
+                state.live += 1;
+                drop(state);
+                return match self.launch() {
+                    Ok(fs) => Ok(Lease::new(self, fs)),
+                    Err(error) => {
+                        self.lock().live -= 1;
+                        self.returned.notify_one();
+                        Err(error)
+                    }
+                };
+            }
+            state = self
+                .returned
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Lease an idle appliance, `None` if they are all in use
+    pub fn try_lease(&self) -> Option<Lease<'_>> {
+        let fs = self.lock().idle.pop()?;
+        Some(Lease::new(self, fs))
+    }
+
+    fn give_back(&self, mut fs: GuestFs<'static, Launched>, handlers: &BTreeSet<u64>) {
+        // without a handler, a failed reset is not reported anywhere
+        fs.clear_error_handler();
+        // the closures of the lessee must not see the calls of the next one
+        fs.remove_handlers_except(handlers);
+        let reset = fs.umount_all();
+
+        let mut state = self.lock();
+        match reset {
+            Ok(()) => state.idle.push(fs),
+            Err(_) => {
+                state.live -= 1;
+                // closing the handle may take a while
+                drop(state);
+                drop(fs);
+            }
+        }
+        self.returned.notify_one();
+    }
+
+    fn close(&self, fs: GuestFs<'static, Launched>) {
+        drop(fs);
+        self.lock().live -= 1;
+        self.returned.notify_one();
+    }
+}
+
+impl fmt::Debug for GuestFsPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("GuestFsPool")
+            // the options may hold credentials
+            .field("drives", &self.drives.len())
+            .field("size", &self.size)
+            .field("live", &state.live)
+            .field("idle", &state.idle.len())
+            .finish()
+    }
+}
+
+/// An appliance leased from a [`GuestFsPool`], which is returned to the pool when dropped
+///
+/// It dereferences to the [`GuestFs`] of the appliance.
+pub struct Lease<'p> {
+    pool: &'p GuestFsPool,
+    // only `None` while it is being dropped
+    fs: Option<GuestFs<'static, Launched>>,
+    // the event closures registered before the lease, e.g. to forward logs
+    handlers: BTreeSet<u64>,
+}
+
+impl<'p> Lease<'p> {
+    fn new(pool: &'p GuestFsPool, fs: GuestFs<'static, Launched>) -> Self {
+        Self {
+            pool,
+            handlers: fs.handler_ids(),
+            fs: Some(fs),
+        }
+    }
+
+    /// Close the appliance instead of returning it to the pool, e.g. after it timed out
+    ///
+    /// The pool launches a new appliance in its place the next time it runs out of them.
+    pub fn discard(mut self) {
+        if let Some(fs) = self.fs.take() {
+            self.pool.close(fs);
+        }
+    }
+}
+
+impl Deref for Lease<'_> {
+    type Target = GuestFs<'static, Launched>;
+
+    fn deref(&self) -> &Self::Target {
+        self.fs.as_ref().expect("the lease holds an appliance")
+    }
+}
+
+impl DerefMut for Lease<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.fs.as_mut().expect("the lease holds an appliance")
+    }
+}
+
+impl fmt::Debug for Lease<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lease").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        if let Some(fs) = self.fs.take() {
+            self.pool.give_back(fs, &self.handlers);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Progress reporting for long-running calls
+//!
+//! libguestfs emits [`Event::Progress`] while some calls run, e.g. transfers, copies and
+//! `mkfs`. [`GuestFs::with_progress`] listens to them for the duration of a single call only,
+//! and passes them on to an observer at a limited rate.
+use super::{Device, Event, EventMask, GuestFs, Launched};
+use crate::Result;
+use std::{
+    io::{Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Default minimum interval between two updates of a progress observer
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress of a long-running call, see [`GuestFs::with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The amount of work done so far, in units of `total`
+    pub position: u64,
+    /// The total amount of work, in arbitrary units (usually bytes)
+    pub total: u64,
+}
+
+impl Progress {
+    /// The fraction of the work done so far, between `0.0` and `1.0`
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.position as f64 / self.total as f64).min(1.0)
+        }
+    }
+
+    /// Whether the call is done
+    pub fn is_done(&self) -> bool {
+        self.position >= self.total
+    }
+}
+
+/// Limits how often a progress observer is called
+struct Throttle {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    fn ready(&mut self, progress: &Progress) -> bool {
+        let now = Instant::now();
+        // the first and the last update are always passed on
+        let ready = progress.position == 0
+            || progress.is_done()
+            || self
+                .last
+                .is_none_or(|last| now.duration_since(last) >= self.interval);
+        if ready {
+            self.last = Some(now);
+        }
+        ready
+    }
+}
+
+/// A long-running call which reports its progress, see [`GuestFs::with_progress`]
+///
+/// Each method makes a single call, and consumes the observer.
+#[must_use = "progress is only reported for calls made through this"]
+pub struct WithProgress<'g, F> {
+    fs: &'g GuestFs<'g, Launched>,
+    observer: F,
+    interval: Duration,
+}
+
+impl<'g, F: FnMut(&Progress) + Send + 'static> WithProgress<'g, F> {
+    /// Call the observer at most once per `interval`, 100 ms by default
+    ///
+    /// The first and the last update are always passed on. `Duration::ZERO` passes on every
+    /// update sent by the appliance.
+    pub fn throttle(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    fn run<T>(self, call: impl FnOnce(&'g GuestFs<'g, Launched>) -> Result<T>) -> Result<T> {
+        let Self {
+            fs,
+            mut observer,
+            interval,
+        } = self;
+        let mut throttle = Throttle {
+            interval,
+            last: None,
+        };
+        // only events of this call are delivered while the handle is alive
+        let _handle = fs.on_event(EventMask::PROGRESS, move |event| {
+            if let &Event::Progress {
+                position, total, ..
+            } = event
+            {
+                let progress = Progress { position, total };
+                if throttle.ready(&progress) {
+                    observer(&progress);
+                }
+            }
+        })?;
+        call(fs)
+    }
+
+    /// See [`GuestFs::download`]
+    pub fn download(self, path: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+        self.run(|fs| fs.download(path, dest))
+    }
+
+    /// See [`GuestFs::download_to`]
+    pub fn download_to(self, path: impl AsRef<Path>, writer: impl Write + Send) -> Result<u64> {
+        self.run(|fs| fs.download_to(path, writer))
+    }
+
+    /// See [`GuestFs::upload`]
+    pub fn upload(self, src: impl AsRef<Path>, path: impl AsRef<Path>) -> Result<()> {
+        self.run(|fs| fs.upload(src, path))
+    }
+
+    /// See [`GuestFs::upload_from`]
+    pub fn upload_from(self, reader: impl Read + Send, path: impl AsRef<Path>) -> Result<u64> {
+        self.run(|fs| fs.upload_from(reader, path))
+    }
+
+    /// See [`GuestFs::copy_device_to_device`]
+    pub fn copy_device_to_device(
+        self,
+        src: impl AsRef<Device>,
+        dest: impl AsRef<Device>,
+    ) -> Result<()> {
+        self.run(|fs| fs.copy_device_to_device(src, dest))
+    }
+
+    /// See [`GuestFs::fill`]
+    pub fn fill(self, c: u8, len: i32, path: impl AsRef<Path>) -> Result<()> {
+        self.run(|fs| fs.fill(c, len, path))
+    }
+
+    /// See [`GuestFs::zero_device`]
+    pub fn zero_device(self, device: impl AsRef<Device>) -> Result<()> {
+        self.run(|fs| fs.zero_device(device))
+    }
+
+    /// See [`GuestFs::mkfs`]
+    pub fn mkfs(self, fstype: &str, device: impl AsRef<Device>) -> Result<()> {
+        self.run(|fs| fs.mkfs(fstype, device))
+    }
+
+    /// See [`GuestFs::tar_out`]
+    pub fn tar_out(self, directory: impl AsRef<Path>, tarfile: impl AsRef<Path>) -> Result<()> {
+        self.run(|fs| fs.tar_out(directory, tarfile))
+    }
+}
+
+impl GuestFs<'_, Launched> {
+    /// Report the progress of the next call to `observer`
+    ///
+    /// Not every call reports progress, and the appliance only starts reporting it once a call
+    /// has been running for a while, so short calls may never call the observer at all.
+    ///
+    /// ```ignore
+    /// g.with_progress(|progress| eprint!("\r{:.0}%", progress.fraction() * 100.0))
+    ///     .download("/var/lib/images/disk.img", "disk.img")?;
+    /// ```
+    pub fn with_progress<F>(&self, observer: F) -> WithProgress<'_, F>
+    where
+        F: FnMut(&Progress) + Send + 'static,
+    {
+        WithProgress {
+            fs: self,
+            observer,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Report the progress of the next call on a terminal progress bar
+    ///
+    /// The bar is finished once the call is done. See [`progress_bar`] for a ready-made bar.
+    ///
+    /// ```ignore
+    /// g.with_progress_bar(hvk::guestfs::progress_bar("Downloading"))
+    ///     .download("/var/lib/images/disk.img", "disk.img")?;
+    /// ```
+    #[cfg(feature = "indicatif")]
+    pub fn with_progress_bar(
+        &self,
+        bar: indicatif::ProgressBar,
+    ) -> WithProgress<'_, impl FnMut(&Progress) + Send + 'static> {
+        self.with_progress(move |progress| {
+            bar.set_length(progress.total);
+            bar.set_position(progress.position);
+            if progress.is_done() {
+                bar.finish();
+            }
+        })
+    }
+}
+
+/// A terminal progress bar showing `message`, for use with [`GuestFs::with_progress_bar`]
+#[cfg(feature = "indicatif")]
+pub fn progress_bar(message: impl Into<std::borrow::Cow<'static, str>>) -> indicatif::ProgressBar {
+    let style = indicatif::ProgressStyle::with_template("{msg} [{wide_bar}] {percent}% ({eta})")
+        .expect("the progress bar template is valid")
+        .progress_chars("=> ");
+    indicatif::ProgressBar::new(0)
+        .with_style(style)
+        .with_message(message)
+}
@@ -0,0 +1,44 @@
+//! Lifecycle phases of a [`GuestFs`](super::GuestFs) handle
+//!
+//! libguestfs handles go through two phases: first drives and appliance settings are configured,
+//! then the appliance is launched and the handle can be used to access the guest.
+//! hvk tracks the phase in the type of the handle, so calling a function in the wrong phase
+//! fails to compile instead of failing at runtime:
+//!
+//! ```compile_fail
+//! # use hvk::guestfs::{Device, GuestFs};
+//! let mut g = GuestFs::new().unwrap();
+//! // the appliance has not been launched yet
+//! g.mount(Device::new("/dev/sda1").unwrap(), "/").unwrap();
+//! ```
+//!
+//! ```compile_fail
+//! # use hvk::guestfs::GuestFs;
+//! let mut g = GuestFs::new().unwrap().launch().unwrap();
+//! // drives can only be added before launching
+//! g.add_drive("disk.img").unwrap();
+//! ```
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Marker trait for the lifecycle phase of a handle
+///
+/// This trait is sealed and cannot be implemented outside of hvk.
+pub trait State: sealed::Sealed {}
+
+/// The handle has been created, but the appliance is not running yet
+///
+/// Drives can be added and the appliance can be configured in this phase.
+pub enum Configuring {}
+
+/// The appliance has been launched
+///
+/// The guest can be mounted and accessed in this phase.
+pub enum Launched {}
+
+impl sealed::Sealed for Configuring {}
+impl sealed::Sealed for Launched {}
+impl State for Configuring {}
+impl State for Launched {}
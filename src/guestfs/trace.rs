@@ -0,0 +1,120 @@
+//! `tracing` instrumentation of libguestfs calls, enabled by the `tracing` feature
+//!
+//! Every libguestfs call runs in a `guestfs` span at debug level, which records the API name
+//! and arguments, then the status and duration of the call. Log messages of the library and
+//! the appliance are forwarded as events, inside the span of the call which caused them,
+//! instead of being printed to stderr.
+//!
+//! Only guest paths and device names are recorded as arguments, never file contents or drive
+//! credentials. They are escaped and truncated, so they are safe to pass to any log sink.
+use crate::Result;
+use std::ffi::OsStr;
+
+/// The span of a single libguestfs call
+#[cfg(feature = "tracing")]
+pub(super) struct CallSpan {
+    span: tracing::span::EnteredSpan,
+    start: std::time::Instant,
+}
+
+/// The span of a single libguestfs call, which does nothing without the `tracing` feature
+#[cfg(not(feature = "tracing"))]
+pub(super) struct CallSpan;
+
+#[cfg(feature = "tracing")]
+impl CallSpan {
+    pub(super) fn enter(api: &'static str, args: &[&dyn AsRef<OsStr>]) -> Self {
+        let span = tracing::debug_span!(
+            "guestfs",
+            api,
+            args = %sanitize(args),
+            status = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        Self {
+            span: span.entered(),
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub(super) fn finish<T>(self, result: &Result<T>) {
+        let duration = self.start.elapsed();
+        self.span.record("duration_us", duration.as_micros() as u64);
+        match result {
+            Ok(_) => self.span.record("status", "ok"),
+            Err(error) => self
+                .span
+                .record("status", "error")
+                .record("error", tracing::field::display(error)),
+        };
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl CallSpan {
+    #[inline(always)]
+    pub(super) fn enter(_api: &'static str, _args: &[&dyn AsRef<OsStr>]) -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    pub(super) fn finish<T>(self, _result: &Result<T>) {}
+}
+
+// longest argument recorded in full
+#[cfg(feature = "tracing")]
+const MAX_ARG_LEN: usize = 256;
+
+/// Format the arguments of a call as a list of escaped, truncated strings
+#[cfg(feature = "tracing")]
+fn sanitize(args: &[&dyn AsRef<OsStr>]) -> String {
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            // `Path`'s `Debug` escapes quotes, control characters and invalid UTF-8
+            let mut arg = format!("{:?}", std::path::Path::new(arg.as_ref()));
+            if arg.len() > MAX_ARG_LEN {
+                let mut end = MAX_ARG_LEN;
+                while !arg.is_char_boundary(end) {
+                    end -= 1;
+                }
+                arg.truncate(end);
+                arg.push_str("...");
+            }
+            arg
+        })
+        .collect();
+    args.join(", ")
+}
+
+/// Forward the log messages of the library and the appliance to `tracing`
+///
+/// Appliance and library messages are only emitted when the handle is in verbose mode,
+/// and trace messages in trace mode.
+#[cfg(feature = "tracing")]
+pub(super) fn forward_logs<S: super::State>(g: &super::GuestFs<'_, S>) {
+    use super::{Event, EventMask};
+
+    let mask = EventMask::APPLIANCE | EventMask::LIBRARY | EventMask::TRACE | EventMask::WARNING;
+    let handle = g.on_event(mask, |event| {
+        let message = |bytes: &[u8]| {
+            String::from_utf8_lossy(bytes)
+                .trim_end_matches(['\r', '\n'])
+                .to_owned()
+        };
+        match *event {
+            Event::Appliance(bytes) => {
+                tracing::trace!(target: "hvk::appliance", "{}", message(bytes))
+            }
+            Event::Library(bytes) => tracing::debug!(target: "hvk::library", "{}", message(bytes)),
+            Event::Trace(bytes) => tracing::trace!(target: "hvk::trace", "{}", message(bytes)),
+            Event::Warning(bytes) => tracing::warn!(target: "hvk::library", "{}", message(bytes)),
+            _ => {}
+        }
+    });
+    // registering only fails on an invalid handle, and logging is best effort anyway
+    if let Ok(handle) = handle {
+        handle.detach();
+    }
+}
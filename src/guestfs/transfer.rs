@@ -0,0 +1,111 @@
+//! Streaming transfers between guest files and host readers and writers
+//!
+//! libguestfs can only transfer files from and to host paths, so the data is passed through
+//! a pipe which libguestfs opens as `/dev/fd/N`, while a helper thread copies between the pipe
+//! and the host [`Read`] or [`Write`]. Nothing is written to a temporary file.
+//!
+//! If the other end of the pipe goes away, writing to it fails with `EPIPE`. This relies on
+//! `SIGPIPE` being ignored, which is the default for Rust programs.
+
+use super::{GuestFs, Launched};
+use crate::Result;
+use std::{
+    io::{self, Read, Write},
+    os::fd::AsRawFd,
+    path::Path,
+    thread,
+};
+
+fn fd_path(fd: &impl AsRawFd) -> String {
+    format!("/dev/fd/{}", fd.as_raw_fd())
+}
+
+impl GuestFs<'_, Launched> {
+    /// Stream a file from the disk image into `writer`, returning the number of bytes copied
+    pub fn download_to(&self, path: impl AsRef<Path>, writer: impl Write + Send) -> Result<u64> {
+        self.download_with(writer, |dest| self.download(path, dest))
+    }
+
+    /// Stream `size` bytes of a file from the disk image into `writer`, starting at `offset`
+    pub fn download_offset_to(
+        &self,
+        path: impl AsRef<Path>,
+        offset: i64,
+        size: i64,
+        writer: impl Write + Send,
+    ) -> Result<u64> {
+        self.download_with(writer, |dest| {
+            self.download_offset(path, dest, offset, size)
+        })
+    }
+
+    /// Stream everything from `reader` into a file of the disk image, replacing it if it already exists
+    ///
+    /// Returns the number of bytes copied. If `reader` fails, the file in the guest is left
+    /// with whatever was read up to that point.
+    pub fn upload_from(&self, reader: impl Read + Send, path: impl AsRef<Path>) -> Result<u64> {
+        self.upload_with(reader, |src| self.upload(src, path))
+    }
+
+    /// Stream everything from `reader` into an existing file of the disk image, starting at `offset`
+    ///
+    /// See [`upload_offset`](Self::upload_offset) and [`upload_from`](Self::upload_from).
+    pub fn upload_offset_from(
+        &self,
+        reader: impl Read + Send,
+        path: impl AsRef<Path>,
+        offset: i64,
+    ) -> Result<u64> {
+        self.upload_with(reader, |src| self.upload_offset(src, path, offset))
+    }
+
+    fn download_with(
+        &self,
+        mut writer: impl Write + Send,
+        download: impl FnOnce(&str) -> Result<()>,
+    ) -> Result<u64> {
+        let (mut pipe_reader, pipe_writer) = io::pipe()?;
+        thread::scope(|scope| {
+            let copier = scope.spawn(move || {
+                let copied = io::copy(&mut pipe_reader, &mut writer)?;
+                writer.flush()?;
+                Ok::<_, io::Error>(copied)
+            });
+
+            let result = download(&fd_path(&pipe_writer));
+            // libguestfs has closed its own descriptor by now, so this ends the copy
+            drop(pipe_writer);
+            let copied = copier
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+            // a failing writer makes the download fail with EPIPE, so its error comes first
+            let copied = copied?;
+            result?;
+            Ok(copied)
+        })
+    }
+
+    fn upload_with(
+        &self,
+        mut reader: impl Read + Send,
+        upload: impl FnOnce(&str) -> Result<()>,
+    ) -> Result<u64> {
+        let (pipe_reader, mut pipe_writer) = io::pipe()?;
+        thread::scope(|scope| {
+            // the write end is dropped when the copy is done, which ends the upload
+            let copier = scope.spawn(move || io::copy(&mut reader, &mut pipe_writer));
+
+            let result = upload(&fd_path(&pipe_reader));
+            // if the upload stopped early, this makes the copier fail with EPIPE instead of blocking
+            drop(pipe_reader);
+            let copied = copier
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+            // a failed upload makes the copier fail with EPIPE, so its error comes first
+            result?;
+            Ok(copied?)
+        })
+    }
+}
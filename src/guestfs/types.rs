@@ -1,8 +1,72 @@
-use libguestfs_sys::guestfs_stat;
+//! Owned wrappers around the structs returned by libguestfs
+//!
+//! Every wrapper frees the underlying C struct with the matching `guestfs_free_*` function when dropped.
+use std::{borrow::Cow, ffi::CStr, os::raw::c_char};
+
+/// Reads a nullable C string field of a libguestfs struct
+///
+/// # Safety
+///
+/// `ptr` must either be null or point to a valid NUL-terminated string that outlives `'a`
+unsafe fn str_field<'a>(ptr: *const c_char) -> Cow<'a, str> {
+    if ptr.is_null() {
+        Cow::Borrowed("")
+    } else {
+        unsafe { CStr::from_ptr(ptr) }.to_string_lossy()
+    }
+}
+
+/// Reads a fixed-size UUID field of an LVM struct, which is *not* NUL-terminated
+fn uuid_field(uuid: &[c_char; 32]) -> Cow<'_, str> {
+    // Safety: c_char and u8 have the same size and alignment
+    let bytes = unsafe { &*(uuid as *const [c_char; 32] as *const [u8; 32]) };
+    String::from_utf8_lossy(bytes)
+}
 
 /// A DirEntList is a list of directory entries
 pub struct DirEntList {
-    pub inner: *mut libguestfs_sys::guestfs_dirent_list,
+    inner: *mut libguestfs_sys::guestfs_dirent_list,
+}
+
+impl DirEntList {
+    /// Takes ownership of a directory entry list returned by libguestfs
+    ///
+    /// # Safety
+    ///
+    /// `inner` must be a valid, non-null pointer returned by a libguestfs call,
+    /// and must not be freed by anyone else.
+    pub unsafe fn from_raw(inner: *mut libguestfs_sys::guestfs_dirent_list) -> Self {
+        Self { inner }
+    }
+
+    fn entries(&self) -> &[libguestfs_sys::guestfs_dirent] {
+        // Safety: `inner` is valid for as long as `self` is
+        let list = unsafe { &*self.inner };
+        if list.val.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(list.val, list.len as usize) }
+    }
+
+    /// Number of entries in the list
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Whether the list is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Get the entry at `index`
+    pub fn get(&self, index: usize) -> Option<DirEnt<'_>> {
+        self.entries().get(index).map(|inner| DirEnt { inner })
+    }
+
+    /// Iterate over the entries in the list
+    pub fn iter(&self) -> impl Iterator<Item = DirEnt<'_>> {
+        self.entries().iter().map(|inner| DirEnt { inner })
+    }
 }
 
 impl Drop for DirEntList {
@@ -13,60 +77,481 @@ impl Drop for DirEntList {
     }
 }
 
-// todo: Re-implementation of std::fs::File for a singular file
-// we should get data from guestfs::guestfs_stat
-pub struct Stat {
-    inner: *mut guestfs_stat,
+/// A borrowed directory entry, either from a [`DirEntList`] or a [`GuestDirEntry`]
+#[derive(Clone, Copy)]
+pub struct DirEnt<'a> {
+    inner: &'a libguestfs_sys::guestfs_dirent,
 }
 
-impl Drop for Stat {
-    fn drop(&mut self) {
-        unsafe {
-            libguestfs_sys::guestfs_free_stat(self.inner);
-        }
+impl<'a> DirEnt<'a> {
+    /// Inode number of the entry
+    pub fn ino(&self) -> i64 {
+        self.inner.ino
+    }
+
+    /// File type, as a single character code
+    ///
+    /// See [`guestfs_readdir`](https://libguestfs.org/guestfs.3.html#guestfs_readdir) for the meaning of each code.
+    pub fn ftyp(&self) -> u8 {
+        self.inner.ftyp as u8
     }
+
+    /// Name of the entry
+    pub fn name(&self) -> &'a CStr {
+        unsafe { CStr::from_ptr(self.inner.name) }
+    }
+}
+
+/// File information with nanosecond timestamps, as returned by `guestfs_statns`
+pub struct StatNs {
+    inner: *mut libguestfs_sys::guestfs_statns,
 }
 
-pub struct LvmLv {
-    inner: *mut libguestfs_sys::guestfs_lvm_lv,
+impl StatNs {
+    /// Takes ownership of a statns struct returned by libguestfs
+    ///
+    /// # Safety
+    ///
+    /// `inner` must be a valid, non-null pointer returned by a libguestfs call,
+    /// and must not be freed by anyone else.
+    pub unsafe fn from_raw(inner: *mut libguestfs_sys::guestfs_statns) -> Self {
+        Self { inner }
+    }
+
+    /// Access the raw fields
+    pub fn get(&self) -> &libguestfs_sys::guestfs_statns {
+        unsafe { &*self.inner }
+    }
 }
 
-impl Drop for LvmLv {
+impl Drop for StatNs {
     fn drop(&mut self) {
         unsafe {
-            libguestfs_sys::guestfs_free_lvm_lv(self.inner);
+            libguestfs_sys::guestfs_free_statns(self.inner);
         }
     }
 }
 
-pub struct LvmVg {
-    inner: *mut libguestfs_sys::guestfs_lvm_vg,
+/// A list of [`StatNs`] entries, as returned by `guestfs_lstatnslist`
+pub struct StatNsList {
+    inner: *mut libguestfs_sys::guestfs_statns_list,
+}
+
+impl StatNsList {
+    /// Takes ownership of a statns list returned by libguestfs
+    ///
+    /// # Safety
+    ///
+    /// `inner` must be a valid, non-null pointer returned by a libguestfs call,
+    /// and must not be freed by anyone else.
+    pub unsafe fn from_raw(inner: *mut libguestfs_sys::guestfs_statns_list) -> Self {
+        Self { inner }
+    }
+
+    /// Access the raw entries
+    pub fn entries(&self) -> &[libguestfs_sys::guestfs_statns] {
+        // Safety: `inner` is valid for as long as `self` is
+        let list = unsafe { &*self.inner };
+        if list.val.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(list.val, list.len as usize) }
+    }
 }
 
-impl Drop for LvmVg {
+impl Drop for StatNsList {
     fn drop(&mut self) {
         unsafe {
-            libguestfs_sys::guestfs_free_lvm_vg(self.inner);
+            libguestfs_sys::guestfs_free_statns_list(self.inner);
         }
     }
 }
 
-pub struct LvmPv {
-    inner: *mut libguestfs_sys::guestfs_lvm_pv,
-}
+/// Defines an owned list of LVM structs and the borrowed view of its entries
+macro_rules! lvm_list {
+    ($(#[$meta:meta])* $list:ident, $raw_list:ident, $free:ident, $entry:ident, $raw:ident) => {
+        $(#[$meta])*
+        pub struct $list {
+            inner: *mut libguestfs_sys::$raw_list,
+        }
 
-impl Drop for LvmPv {
-    fn drop(&mut self) {
-        unsafe {
-            libguestfs_sys::guestfs_free_lvm_pv(self.inner);
+        impl $list {
+            /// Takes ownership of a list returned by libguestfs
+            ///
+            /// # Safety
+            ///
+            /// `inner` must be a valid, non-null pointer returned by a libguestfs call,
+            /// and must not be freed by anyone else.
+            pub unsafe fn from_raw(inner: *mut libguestfs_sys::$raw_list) -> Self {
+                Self { inner }
+            }
+
+            fn entries(&self) -> &[libguestfs_sys::$raw] {
+                // Safety: `inner` is valid for as long as `self` is
+                let list = unsafe { &*self.inner };
+                if list.val.is_null() {
+                    return &[];
+                }
+                unsafe { std::slice::from_raw_parts(list.val, list.len as usize) }
+            }
+
+            /// Number of entries in the list
+            pub fn len(&self) -> usize {
+                self.entries().len()
+            }
+
+            /// Whether the list is empty
+            pub fn is_empty(&self) -> bool {
+                self.entries().is_empty()
+            }
+
+            /// Get the entry at `index`
+            pub fn get(&self, index: usize) -> Option<$entry<'_>> {
+                self.entries().get(index).map(|inner| $entry { inner })
+            }
+
+            /// Iterate over the entries in the list
+            pub fn iter(&self) -> impl Iterator<Item = $entry<'_>> {
+                self.entries().iter().map(|inner| $entry { inner })
+            }
+        }
+
+        impl Drop for $list {
+            fn drop(&mut self) {
+                unsafe {
+                    libguestfs_sys::$free(self.inner);
+                }
+            }
         }
+    };
+}
+
+lvm_list!(
+    /// A list of LVM logical volumes, as returned by `guestfs_lvs_full`
+    LvmLvList,
+    guestfs_lvm_lv_list,
+    guestfs_free_lvm_lv_list,
+    LvmLv,
+    guestfs_lvm_lv
+);
+
+/// A borrowed LVM logical volume from an [`LvmLvList`]
+///
+/// See `lvs(8)` for the meaning of the fields; text fields which are not set are empty.
+#[derive(Clone, Copy)]
+pub struct LvmLv<'a> {
+    inner: &'a libguestfs_sys::guestfs_lvm_lv,
+}
+
+impl<'a> LvmLv<'a> {
+    /// Name of the logical volume in its volume group, e.g. `root`
+    pub fn name(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.lv_name) }
+    }
+
+    /// UUID of the logical volume, without dashes
+    pub fn uuid(&self) -> Cow<'a, str> {
+        uuid_field(&self.inner.lv_uuid)
+    }
+
+    /// Attribute flags, e.g. `-wi-a-----`
+    pub fn attr(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.lv_attr) }
+    }
+
+    /// Persistent major device number, `-1` if not set
+    pub fn major(&self) -> i64 {
+        self.inner.lv_major
+    }
+
+    /// Persistent minor device number, `-1` if not set
+    pub fn minor(&self) -> i64 {
+        self.inner.lv_minor
+    }
+
+    /// Major device number in the appliance, `-1` if the volume is not active
+    pub fn kernel_major(&self) -> i64 {
+        self.inner.lv_kernel_major
+    }
+
+    /// Minor device number in the appliance, `-1` if the volume is not active
+    pub fn kernel_minor(&self) -> i64 {
+        self.inner.lv_kernel_minor
+    }
+
+    /// Size of the logical volume, in bytes
+    pub fn size(&self) -> u64 {
+        self.inner.lv_size
+    }
+
+    /// Number of segments
+    pub fn seg_count(&self) -> i64 {
+        self.inner.seg_count
+    }
+
+    /// The volume this is a snapshot of, empty if it is not a snapshot
+    pub fn origin(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.origin) }
+    }
+
+    /// Snapshot usage, or `None` if the volume is not a snapshot
+    pub fn snap_percent(&self) -> Option<f32> {
+        Some(self.inner.snap_percent).filter(|p| *p >= 0.0)
+    }
+
+    /// Mirror copy progress, or `None` if the volume is not a mirror
+    pub fn copy_percent(&self) -> Option<f32> {
+        Some(self.inner.copy_percent).filter(|p| *p >= 0.0)
+    }
+
+    /// The physical volume being moved away from by `pvmove`, if any
+    pub fn move_pv(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.move_pv) }
+    }
+
+    /// Tags, separated by commas
+    pub fn tags(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.lv_tags) }
+    }
+
+    /// The mirror log volume, if this is a mirror
+    pub fn mirror_log(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.mirror_log) }
+    }
+
+    /// Kernel device mapper modules needed by the volume, e.g. `mirror`
+    pub fn modules(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.modules) }
+    }
+}
+
+lvm_list!(
+    /// A list of LVM volume groups, as returned by `guestfs_vgs_full`
+    LvmVgList,
+    guestfs_lvm_vg_list,
+    guestfs_free_lvm_vg_list,
+    LvmVg,
+    guestfs_lvm_vg
+);
+
+/// A borrowed LVM volume group from an [`LvmVgList`]
+///
+/// See `vgs(8)` for the meaning of the fields; text fields which are not set are empty.
+#[derive(Clone, Copy)]
+pub struct LvmVg<'a> {
+    inner: &'a libguestfs_sys::guestfs_lvm_vg,
+}
+
+impl<'a> LvmVg<'a> {
+    /// Name of the volume group, e.g. `vg0`
+    pub fn name(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.vg_name) }
+    }
+
+    /// UUID of the volume group, without dashes
+    pub fn uuid(&self) -> Cow<'a, str> {
+        uuid_field(&self.inner.vg_uuid)
+    }
+
+    /// Metadata format, e.g. `lvm2`
+    pub fn fmt(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.vg_fmt) }
+    }
+
+    /// Attribute flags, e.g. `wz--n-`
+    pub fn attr(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.vg_attr) }
+    }
+
+    /// Size of the volume group, in bytes
+    pub fn size(&self) -> u64 {
+        self.inner.vg_size
+    }
+
+    /// Free space in the volume group, in bytes
+    pub fn free(&self) -> u64 {
+        self.inner.vg_free
+    }
+
+    /// System ID of the host owning the volume group, empty if not set
+    pub fn sysid(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.vg_sysid) }
+    }
+
+    /// Size of a physical extent, in bytes
+    pub fn extent_size(&self) -> u64 {
+        self.inner.vg_extent_size
+    }
+
+    /// Total number of physical extents
+    pub fn extent_count(&self) -> i64 {
+        self.inner.vg_extent_count
+    }
+
+    /// Number of free physical extents
+    pub fn free_count(&self) -> i64 {
+        self.inner.vg_free_count
+    }
+
+    /// Maximum number of logical volumes, `0` if unlimited
+    pub fn max_lv(&self) -> i64 {
+        self.inner.max_lv
+    }
+
+    /// Maximum number of physical volumes, `0` if unlimited
+    pub fn max_pv(&self) -> i64 {
+        self.inner.max_pv
+    }
+
+    /// Number of physical volumes
+    pub fn pv_count(&self) -> i64 {
+        self.inner.pv_count
+    }
+
+    /// Number of logical volumes
+    pub fn lv_count(&self) -> i64 {
+        self.inner.lv_count
+    }
+
+    /// Number of snapshots
+    pub fn snap_count(&self) -> i64 {
+        self.inner.snap_count
+    }
+
+    /// Revision number of the metadata
+    pub fn seqno(&self) -> i64 {
+        self.inner.vg_seqno
+    }
+
+    /// Tags, separated by commas
+    pub fn tags(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.vg_tags) }
+    }
+
+    /// Number of metadata areas
+    pub fn mda_count(&self) -> i64 {
+        self.inner.vg_mda_count
+    }
+
+    /// Free space in the metadata areas, in bytes
+    pub fn mda_free(&self) -> u64 {
+        self.inner.vg_mda_free
+    }
+}
+
+lvm_list!(
+    /// A list of LVM physical volumes, as returned by `guestfs_pvs_full`
+    LvmPvList,
+    guestfs_lvm_pv_list,
+    guestfs_free_lvm_pv_list,
+    LvmPv,
+    guestfs_lvm_pv
+);
+
+/// A borrowed LVM physical volume from an [`LvmPvList`]
+///
+/// See `pvs(8)` for the meaning of the fields; text fields which are not set are empty.
+#[derive(Clone, Copy)]
+pub struct LvmPv<'a> {
+    inner: &'a libguestfs_sys::guestfs_lvm_pv,
+}
+
+impl<'a> LvmPv<'a> {
+    /// Device of the physical volume, e.g. `/dev/sda2`
+    pub fn name(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.pv_name) }
+    }
+
+    /// UUID of the physical volume, without dashes
+    pub fn uuid(&self) -> Cow<'a, str> {
+        uuid_field(&self.inner.pv_uuid)
+    }
+
+    /// Metadata format, e.g. `lvm2`
+    pub fn fmt(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.pv_fmt) }
+    }
+
+    /// Size of the physical volume, in bytes
+    pub fn size(&self) -> u64 {
+        self.inner.pv_size
+    }
+
+    /// Size of the underlying device, in bytes
+    pub fn dev_size(&self) -> u64 {
+        self.inner.dev_size
+    }
+
+    /// Free space, in bytes
+    pub fn free(&self) -> u64 {
+        self.inner.pv_free
+    }
+
+    /// Used space, in bytes
+    pub fn used(&self) -> u64 {
+        self.inner.pv_used
+    }
+
+    /// Attribute flags, e.g. `a--`
+    pub fn attr(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.pv_attr) }
+    }
+
+    /// Total number of physical extents
+    pub fn pe_count(&self) -> i64 {
+        self.inner.pv_pe_count
+    }
+
+    /// Number of allocated physical extents
+    pub fn pe_alloc_count(&self) -> i64 {
+        self.inner.pv_pe_alloc_count
+    }
+
+    /// Tags, separated by commas
+    pub fn tags(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.pv_tags) }
+    }
+
+    /// Offset of the first physical extent, in bytes
+    pub fn pe_start(&self) -> u64 {
+        self.inner.pe_start
+    }
+
+    /// Number of metadata areas
+    pub fn mda_count(&self) -> i64 {
+        self.inner.pv_mda_count
+    }
+
+    /// Free space in the metadata areas, in bytes
+    pub fn mda_free(&self) -> u64 {
+        self.inner.pv_mda_free
     }
 }
 
+/// A single, separately allocated directory entry
 pub struct GuestDirEntry {
     inner: *mut libguestfs_sys::guestfs_dirent,
 }
 
+impl GuestDirEntry {
+    /// Takes ownership of a directory entry returned by libguestfs
+    ///
+    /// # Safety
+    ///
+    /// `inner` must be a valid, non-null pointer returned by a libguestfs call,
+    /// and must not be freed by anyone else.
+    pub unsafe fn from_raw(inner: *mut libguestfs_sys::guestfs_dirent) -> Self {
+        Self { inner }
+    }
+
+    /// Borrow the entry
+    pub fn entry(&self) -> DirEnt<'_> {
+        DirEnt {
+            inner: unsafe { &*self.inner },
+        }
+    }
+}
+
 impl Drop for GuestDirEntry {
     fn drop(&mut self) {
         unsafe {
@@ -74,3 +559,129 @@ impl Drop for GuestDirEntry {
         }
     }
 }
+
+/// A list of installed applications, as returned by `guestfs_inspect_list_applications2`
+pub struct ApplicationList {
+    inner: *mut libguestfs_sys::guestfs_application2_list,
+}
+
+impl ApplicationList {
+    /// Takes ownership of an application list returned by libguestfs
+    ///
+    /// # Safety
+    ///
+    /// `inner` must be a valid, non-null pointer returned by a libguestfs call,
+    /// and must not be freed by anyone else.
+    pub unsafe fn from_raw(inner: *mut libguestfs_sys::guestfs_application2_list) -> Self {
+        Self { inner }
+    }
+
+    fn entries(&self) -> &[libguestfs_sys::guestfs_application2] {
+        // Safety: `inner` is valid for as long as `self` is
+        let list = unsafe { &*self.inner };
+        if list.val.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(list.val, list.len as usize) }
+    }
+
+    /// Number of applications in the list
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Whether the list is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Get the application at `index`
+    pub fn get(&self, index: usize) -> Option<Application2<'_>> {
+        self.entries()
+            .get(index)
+            .map(|inner| Application2 { inner })
+    }
+
+    /// Iterate over the applications in the list
+    pub fn iter(&self) -> impl Iterator<Item = Application2<'_>> {
+        self.entries().iter().map(|inner| Application2 { inner })
+    }
+}
+
+impl Drop for ApplicationList {
+    fn drop(&mut self) {
+        unsafe {
+            libguestfs_sys::guestfs_free_application2_list(self.inner);
+        }
+    }
+}
+
+/// A borrowed installed application from an [`ApplicationList`]
+///
+/// Fields which are not known are empty, see
+/// [`guestfs_inspect_list_applications2`](https://libguestfs.org/guestfs.3.html#guestfs_inspect_list_applications2).
+#[derive(Clone, Copy)]
+pub struct Application2<'a> {
+    inner: &'a libguestfs_sys::guestfs_application2,
+}
+
+impl<'a> Application2<'a> {
+    /// Package name, e.g. `bash`
+    pub fn name(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_name) }
+    }
+
+    /// Name to display to users, which is the package name on Linux
+    pub fn display_name(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_display_name) }
+    }
+
+    /// Package epoch, `0` if there is none
+    pub fn epoch(&self) -> i32 {
+        self.inner.app2_epoch
+    }
+
+    pub fn version(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_version) }
+    }
+
+    pub fn release(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_release) }
+    }
+
+    pub fn arch(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_arch) }
+    }
+
+    /// Installation directory, only known on Windows
+    pub fn install_path(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_install_path) }
+    }
+
+    /// Installation directory in the guest's language, only known on Windows
+    pub fn trans_path(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_trans_path) }
+    }
+
+    pub fn publisher(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_publisher) }
+    }
+
+    pub fn url(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_url) }
+    }
+
+    /// Name of the source package the package was built from
+    pub fn source_package(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_source_package) }
+    }
+
+    /// One-line description
+    pub fn summary(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_summary) }
+    }
+
+    pub fn description(&self) -> Cow<'a, str> {
+        unsafe { str_field(self.inner.app2_description) }
+    }
+}
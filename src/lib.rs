@@ -1,10 +1,20 @@
-mod error;
-mod filesystem;
-mod guestfs;
+//! Idiomatic Rust bindings for libguestfs
+//!
+//! The crate is split into two layers:
+//!
+//! - [`guestfs`] (also available as [`raw`]) is a thin, mostly 1:1 wrapper around the libguestfs C API
+//! - [`fs`] is a high-level interface modeled after [`std::fs`]
+//!
+//! Most users will want to start with [`prelude`].
+pub mod error;
+pub mod fs;
+pub mod guestfs;
+pub mod prelude;
+#[cfg(feature = "sbom")]
+pub mod sbom;
 
-type Result<T> = std::result::Result<T, crate::error::Error>;
-use std::{
-    ffi::{CStr, CString},
-    path::{Path, PathBuf},
-};
-// We should be re-implementing features from std::fs into here
+pub use error::Error;
+pub use guestfs as raw;
+
+/// Result type used throughout hvk
+pub type Result<T> = std::result::Result<T, crate::error::Error>;
@@ -0,0 +1,17 @@
+//! Commonly used types, re-exported for convenience
+//!
+//! ```ignore
+//! use hvk::prelude::*;
+//! ```
+pub use crate::{
+    error::Error,
+    fs::{
+        AclType, DirEntry, FileType, GuestFile, GuestFileSystem, Metadata, MountMode, MountOptions,
+        OpenOptions, OsInfo, Permissions, ReadDir,
+    },
+    guestfs::{
+        Configuring, Device, DriveFormat, DriveOptions, GuestFs, Launched, LogicalVolume,
+        Mountable, Partition,
+    },
+    Result,
+};
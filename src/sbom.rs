@@ -0,0 +1,563 @@
+//! Software bills of materials of disk images, enabled by the `sbom` feature
+//!
+//! An [`Sbom`] lists the operating systems found on a disk image by [`GuestFileSystem::inspect`],
+//! and the applications installed in them as listed by
+//! [`GuestFileSystem::list_applications`]. It can be written as
+//! [SPDX 2.3](https://spdx.github.io/spdx-spec/v2.3/) or
+//! [CycloneDX 1.5](https://cyclonedx.org/docs/1.5/json/) JSON.
+//!
+//! Packages are identified by [package URLs](https://github.com/package-url/purl-spec).
+//! Licenses are not known to libguestfs, so they are always `NOASSERTION` in SPDX documents.
+//!
+//! ```ignore
+//! use hvk::{guestfs::DriveFormat, sbom::Sbom};
+//!
+//! let sbom = Sbom::from_image("fedora.qcow2", DriveFormat::Qcow2)?;
+//! sbom.write_spdx(std::fs::File::create("fedora.spdx.json")?)?;
+//! sbom.write_cyclonedx(std::fs::File::create("fedora.cdx.json")?)?;
+//! ```
+use crate::{
+    error::Error,
+    fs::{Application, GuestFileSystem, OsInfo, PackageFormat},
+    guestfs::{DriveFormat, DriveOptions, GuestFs},
+    Result,
+};
+use serde_json::{json, Map, Value};
+use std::{
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+const TOOL_NAME: &str = env!("CARGO_PKG_NAME");
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// An operating system listed in an [`Sbom`], with its applications
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct System {
+    /// The operating system
+    pub os: OsInfo,
+    /// The applications installed in it
+    pub applications: Vec<Application>,
+}
+
+/// A software bill of materials of a disk image
+#[derive(Debug, Clone)]
+pub struct Sbom {
+    name: String,
+    systems: Vec<System>,
+    created: SystemTime,
+    // identifies this SBOM in both formats
+    serial: Uuid,
+}
+
+impl Sbom {
+    /// Create an SBOM named `name`, usually after the disk image, of the operating systems
+    /// found by [`GuestFileSystem::inspect`]
+    pub fn new(name: impl Into<String>, systems: Vec<System>) -> Self {
+        Self {
+            name: name.into(),
+            systems,
+            created: SystemTime::now(),
+            serial: Uuid::new_v4(),
+        }
+    }
+
+    /// Launch an appliance with the disk image at `path` attached read-only, inspect it and
+    /// list the applications of every operating system found
+    ///
+    /// The format of the image has to be given, since images of unknown origin must not be
+    /// probed, see [`DriveFormat`]. The SBOM is named after the file name of the image.
+    pub fn from_image(path: impl AsRef<Path>, format: DriveFormat) -> Result<Self> {
+        let path = path.as_ref();
+        let mut g = GuestFs::new()?;
+        g.add_drive_opts(&DriveOptions::new(path, format).readonly(true))?;
+        let mut fs = GuestFileSystem::from(g.launch()?);
+        let mut systems = Vec::new();
+        for os in fs.inspect()? {
+            let applications = fs.list_applications(&os)?;
+            systems.push(System { os, applications });
+        }
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        Ok(Self::new(name, systems))
+    }
+
+    /// The name of the SBOM
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The operating systems listed in the SBOM
+    pub fn systems(&self) -> &[System] {
+        &self.systems
+    }
+
+    /// The SBOM as an SPDX 2.3 JSON document
+    pub fn spdx(&self) -> Value {
+        let mut packages = Vec::new();
+        let mut relationships = Vec::new();
+        for (i, System { os, applications }) in self.systems.iter().enumerate() {
+            let os_id = format!("SPDXRef-OperatingSystem-{i}");
+            let mut package = Map::new();
+            package.insert("SPDXID".into(), json!(os_id));
+            package.insert("name".into(), json!(os.distro.as_str()));
+            package.insert("versionInfo".into(), json!(os_version(os)));
+            package.insert("primaryPackagePurpose".into(), json!("OPERATING-SYSTEM"));
+            insert_some(&mut package, "description", os.product_name.as_deref());
+            insert_noassertions(&mut package);
+            packages.push(Value::Object(package));
+            relationships.push(json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": os_id,
+            }));
+
+            for (j, app) in applications.iter().enumerate() {
+                let app_id = format!("SPDXRef-Package-{i}-{j}");
+                let mut package = Map::new();
+                package.insert("SPDXID".into(), json!(app_id));
+                package.insert("name".into(), json!(app.name));
+                package.insert("versionInfo".into(), json!(app.evr()));
+                let supplier = app.publisher.as_ref().map_or_else(
+                    || "NOASSERTION".to_owned(),
+                    |publisher| format!("Organization: {publisher}"),
+                );
+                package.insert("supplier".into(), json!(supplier));
+                insert_some(&mut package, "homepage", app.url.as_deref());
+                if let Some(source) = &app.source_package {
+                    package.insert(
+                        "sourceInfo".into(),
+                        json!(format!("built from source package {source}")),
+                    );
+                }
+                insert_some(&mut package, "summary", app.summary.as_deref());
+                insert_some(&mut package, "description", app.description.as_deref());
+                package.insert(
+                    "externalRefs".into(),
+                    json!([{
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": purl(os, app),
+                    }]),
+                );
+                insert_noassertions(&mut package);
+                packages.push(Value::Object(package));
+                relationships.push(json!({
+                    "spdxElementId": os_id,
+                    "relationshipType": "CONTAINS",
+                    "relatedSpdxElement": app_id,
+                }));
+            }
+        }
+
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": self.name,
+            "documentNamespace": format!(
+                "https://spdx.org/spdxdocs/{}-{}",
+                purl_encode(&self.name),
+                self.serial
+            ),
+            "creationInfo": {
+                "created": timestamp(self.created),
+                "creators": [format!("Tool: {TOOL_NAME}-{TOOL_VERSION}")],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+
+    /// The SBOM as a CycloneDX 1.5 JSON document
+    pub fn cyclonedx(&self) -> Value {
+        let mut components = Vec::new();
+        let mut dependencies = Vec::new();
+        let mut systems = Vec::new();
+        for (i, System { os, applications }) in self.systems.iter().enumerate() {
+            let os_ref = format!("os-{i}");
+            let mut component = Map::new();
+            component.insert("type".into(), json!("operating-system"));
+            component.insert("bom-ref".into(), json!(os_ref));
+            component.insert("name".into(), json!(os.distro.as_str()));
+            component.insert("version".into(), json!(os_version(os)));
+            insert_some(&mut component, "description", os.product_name.as_deref());
+            components.push(Value::Object(component));
+
+            let mut packages = Vec::new();
+            for (j, app) in applications.iter().enumerate() {
+                let app_ref = format!("os-{i}-package-{j}");
+                let mut component = Map::new();
+                component.insert("type".into(), json!("library"));
+                component.insert("bom-ref".into(), json!(app_ref));
+                component.insert("name".into(), json!(app.name));
+                component.insert("version".into(), json!(app.evr()));
+                insert_some(&mut component, "publisher", app.publisher.as_deref());
+                insert_some(&mut component, "description", app.summary.as_deref());
+                component.insert("purl".into(), json!(purl(os, app)));
+                if let Some(url) = &app.url {
+                    component.insert(
+                        "externalReferences".into(),
+                        json!([{ "type": "website", "url": url }]),
+                    );
+                }
+                if let Some(source) = &app.source_package {
+                    component.insert(
+                        "properties".into(),
+                        json!([{ "name": "hvk:package:source", "value": source }]),
+                    );
+                }
+                components.push(Value::Object(component));
+                packages.push(app_ref);
+            }
+            dependencies.push(json!({ "ref": os_ref, "dependsOn": packages }));
+            systems.push(os_ref);
+        }
+        dependencies.insert(0, json!({ "ref": "image", "dependsOn": systems }));
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "serialNumber": self.serial.urn().to_string(),
+            "version": 1,
+            "metadata": {
+                "timestamp": timestamp(self.created),
+                "tools": {
+                    "components": [{
+                        "type": "library",
+                        "name": TOOL_NAME,
+                        "version": TOOL_VERSION,
+                    }],
+                },
+                "component": {
+                    "type": "file",
+                    "bom-ref": "image",
+                    "name": self.name,
+                },
+            },
+            "components": components,
+            "dependencies": dependencies,
+        })
+    }
+
+    /// Write the SBOM as an SPDX 2.3 JSON document
+    pub fn write_spdx(&self, writer: impl Write) -> Result<()> {
+        write_json(writer, &self.spdx())
+    }
+
+    /// Write the SBOM as a CycloneDX 1.5 JSON document
+    pub fn write_cyclonedx(&self, writer: impl Write) -> Result<()> {
+        write_json(writer, &self.cyclonedx())
+    }
+}
+
+fn write_json(mut writer: impl Write, document: &Value) -> Result<()> {
+    serde_json::to_writer_pretty(&mut writer, document).map_err(|e| Error::Io(e.into()))?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+fn insert_some(map: &mut Map<String, Value>, key: &str, value: Option<&str>) {
+    if let Some(value) = value {
+        map.insert(key.into(), json!(value));
+    }
+}
+
+/// Fill in the fields SPDX requires, which libguestfs knows nothing about
+fn insert_noassertions(package: &mut Map<String, Value>) {
+    for key in [
+        "downloadLocation",
+        "licenseConcluded",
+        "licenseDeclared",
+        "copyrightText",
+    ] {
+        package.insert(key.into(), json!("NOASSERTION"));
+    }
+    package.insert("filesAnalyzed".into(), json!(false));
+}
+
+fn os_version(os: &OsInfo) -> String {
+    format!("{}.{}", os.major_version, os.minor_version)
+}
+
+/// The package URL of an application, e.g. `pkg:rpm/fedora/bash@5.2.26-3.fc40?arch=x86_64`
+fn purl(os: &OsInfo, app: &Application) -> String {
+    let purl_type = match os.package_format {
+        PackageFormat::Rpm => "rpm",
+        PackageFormat::Deb => "deb",
+        PackageFormat::Apk => "apk",
+        PackageFormat::Pacman => "alpm",
+        _ => "generic",
+    };
+    let mut purl = format!("pkg:{purl_type}/");
+    if purl_type != "generic" {
+        purl.push_str(&purl_encode(os.distro.as_str()));
+        purl.push('/');
+    }
+    purl.push_str(&purl_encode(&app.name));
+
+    let mut qualifiers = Vec::new();
+    if let Some(arch) = &app.arch {
+        qualifiers.push(format!("arch={}", purl_encode(arch)));
+    }
+    // RPM package URLs carry the epoch as a qualifier, the others in the version
+    let version = if purl_type == "rpm" {
+        if app.epoch != 0 {
+            qualifiers.push(format!("epoch={}", app.epoch));
+        }
+        match &app.release {
+            Some(release) => format!("{}-{release}", app.version),
+            None => app.version.clone(),
+        }
+    } else {
+        app.evr()
+    };
+    if !version.is_empty() {
+        purl.push('@');
+        purl.push_str(&purl_encode(&version));
+    }
+    if !qualifiers.is_empty() {
+        purl.push('?');
+        purl.push_str(&qualifiers.join("&"));
+    }
+    purl
+}
+
+/// Percent-encode everything but unreserved characters, as package URLs require
+fn purl_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b".-_~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Format a time as an RFC 3339 UTC timestamp with second precision, e.g. `2024-05-01T12:00:00Z`
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::{Distro, OsType, PackageManager};
+    use std::{collections::BTreeMap, time::Duration};
+
+    fn os(distro: Distro, package_format: PackageFormat) -> OsInfo {
+        OsInfo {
+            root: "/dev/sda1".parse().unwrap(),
+            os_type: OsType::Linux,
+            distro,
+            major_version: 40,
+            minor_version: 0,
+            product_name: Some("Fedora Linux 40 (Forty)".into()),
+            product_variant: None,
+            arch: Some("x86_64".into()),
+            hostname: None,
+            package_format,
+            package_manager: PackageManager::Dnf,
+            osinfo: Some("fedora40".into()),
+            drive_mappings: BTreeMap::new(),
+            mountpoints: BTreeMap::new(),
+        }
+    }
+
+    fn app(name: &str, epoch: i32, version: &str, release: Option<&str>) -> Application {
+        Application {
+            name: name.into(),
+            epoch,
+            version: version.into(),
+            release: release.map(Into::into),
+            arch: Some("x86_64".into()),
+            install_path: None,
+            publisher: Some("Fedora Project".into()),
+            url: Some("https://www.vim.org/".into()),
+            source_package: Some("vim".into()),
+            summary: Some("A version of the VIM editor".into()),
+            description: None,
+        }
+    }
+
+    fn sbom() -> Sbom {
+        let fedora = os(Distro::Fedora, PackageFormat::Rpm);
+        Sbom {
+            name: "fedora 40.qcow2".into(),
+            systems: vec![System {
+                os: fedora,
+                applications: vec![
+                    app("bash", 0, "5.2.26", Some("3.fc40")),
+                    app("vim-enhanced", 2, "9.1.0", Some("1.fc40")),
+                ],
+            }],
+            created: UNIX_EPOCH + Duration::from_secs(1_714_564_800),
+            serial: Uuid::nil(),
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        for (secs, expected) in [
+            (0, "1970-01-01T00:00:00Z"),
+            (951_782_400, "2000-02-29T00:00:00Z"),
+            (1_714_564_800, "2024-05-01T12:00:00Z"),
+            (1_735_689_599, "2024-12-31T23:59:59Z"),
+            (253_402_300_799, "9999-12-31T23:59:59Z"),
+        ] {
+            assert_eq!(timestamp(UNIX_EPOCH + Duration::from_secs(secs)), expected);
+        }
+        // clocks before the epoch are clamped to it
+        let before = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(timestamp(before), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn percent_encoding() {
+        assert_eq!(purl_encode("AZaz09.-_~"), "AZaz09.-_~");
+        assert_eq!(purl_encode("a b/c+d:e@f"), "a%20b%2Fc%2Bd%3Ae%40f");
+        assert_eq!(purl_encode("é"), "%C3%A9");
+    }
+
+    #[test]
+    fn package_urls() {
+        let fedora = os(Distro::Fedora, PackageFormat::Rpm);
+        assert_eq!(
+            purl(&fedora, &app("bash", 0, "5.2.26", Some("3.fc40"))),
+            "pkg:rpm/fedora/bash@5.2.26-3.fc40?arch=x86_64"
+        );
+        assert_eq!(
+            purl(&fedora, &app("vim-enhanced", 2, "9.1.0", Some("1.fc40"))),
+            "pkg:rpm/fedora/vim-enhanced@9.1.0-1.fc40?arch=x86_64&epoch=2"
+        );
+
+        // other formats keep the epoch in the version
+        let debian = os(Distro::Debian, PackageFormat::Deb);
+        assert_eq!(
+            purl(&debian, &app("libc6", 1, "2.36", Some("9"))),
+            "pkg:deb/debian/libc6@1%3A2.36-9?arch=x86_64"
+        );
+
+        let mut unknown = os(Distro::Unknown, PackageFormat::Unknown);
+        unknown.arch = None;
+        let mut tool = app("tool", 0, "", None);
+        tool.arch = None;
+        assert_eq!(purl(&unknown, &tool), "pkg:generic/tool");
+    }
+
+    #[test]
+    fn spdx_has_the_required_fields() {
+        let doc = sbom().spdx();
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["dataLicense"], "CC0-1.0");
+        assert_eq!(doc["SPDXID"], "SPDXRef-DOCUMENT");
+        assert_eq!(doc["name"], "fedora 40.qcow2");
+        assert_eq!(
+            doc["documentNamespace"],
+            "https://spdx.org/spdxdocs/fedora%2040.qcow2-00000000-0000-0000-0000-000000000000"
+        );
+        assert_eq!(doc["creationInfo"]["created"], "2024-05-01T12:00:00Z");
+        assert_eq!(
+            doc["creationInfo"]["creators"][0],
+            format!("Tool: hvk-{TOOL_VERSION}")
+        );
+
+        let packages = doc["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 3);
+        for package in packages {
+            for field in [
+                "SPDXID",
+                "name",
+                "versionInfo",
+                "downloadLocation",
+                "licenseConcluded",
+                "licenseDeclared",
+                "copyrightText",
+            ] {
+                assert!(package[field].is_string(), "{field} missing in {package}");
+            }
+            assert_eq!(package["filesAnalyzed"], false);
+        }
+        assert_eq!(packages[0]["primaryPackagePurpose"], "OPERATING-SYSTEM");
+        assert_eq!(packages[0]["versionInfo"], "40.0");
+        let vim = &packages[2];
+        assert_eq!(vim["versionInfo"], "2:9.1.0-1.fc40");
+        assert_eq!(vim["supplier"], "Organization: Fedora Project");
+        assert_eq!(
+            vim["externalRefs"][0]["referenceLocator"],
+            "pkg:rpm/fedora/vim-enhanced@9.1.0-1.fc40?arch=x86_64&epoch=2"
+        );
+
+        let relationships = doc["relationships"].as_array().unwrap();
+        assert_eq!(relationships.len(), 3);
+        assert_eq!(relationships[0]["relationshipType"], "DESCRIBES");
+        assert_eq!(relationships[2]["relationshipType"], "CONTAINS");
+        assert_eq!(
+            relationships[2]["relatedSpdxElement"],
+            "SPDXRef-Package-0-1"
+        );
+    }
+
+    #[test]
+    fn cyclonedx_links_packages_to_their_os() {
+        let doc = sbom().cyclonedx();
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        assert_eq!(doc["specVersion"], "1.5");
+        assert_eq!(
+            doc["serialNumber"],
+            "urn:uuid:00000000-0000-0000-0000-000000000000"
+        );
+        assert_eq!(doc["metadata"]["timestamp"], "2024-05-01T12:00:00Z");
+        assert_eq!(doc["metadata"]["component"]["name"], "fedora 40.qcow2");
+
+        let components = doc["components"].as_array().unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0]["type"], "operating-system");
+        assert_eq!(components[2]["bom-ref"], "os-0-package-1");
+        assert_eq!(components[2]["version"], "2:9.1.0-1.fc40");
+
+        assert_eq!(
+            doc["dependencies"],
+            json!([
+                { "ref": "image", "dependsOn": ["os-0"] },
+                { "ref": "os-0", "dependsOn": ["os-0-package-0", "os-0-package-1"] },
+            ])
+        );
+    }
+
+    #[test]
+    fn written_documents_end_with_a_newline() {
+        let mut json = Vec::new();
+        sbom().write_spdx(&mut json).unwrap();
+        assert_eq!(json.last(), Some(&b'\n'));
+        let parsed: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed, sbom().spdx());
+    }
+}
@@ -0,0 +1,70 @@
+//! Uploads and downloads can be cancelled, and calls can be given a deadline
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::scratch;
+use hvk::Error;
+use std::{
+    io::{self, Write},
+    thread,
+    time::Duration,
+};
+
+/// A writer which is too slow for any deadline
+struct Slow<W>(W);
+
+impl<W: Write> Write for Slow<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        thread::sleep(Duration::from_millis(100));
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn cancel_download() {
+    let g = scratch();
+    g.fill(0, 32 * 1024 * 1024, "/file").unwrap();
+    let cancel = g.cancel_handle();
+
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        cancel.cancel();
+    });
+    let error = g.download_to("/file", Slow(io::sink())).unwrap_err();
+    canceller.join().unwrap();
+    assert!(matches!(error, Error::Cancelled(_)), "{error:?}");
+
+    // later calls are not affected
+    g.touch("/other").unwrap();
+    assert!(matches!(g.cat("/missing"), Err(Error::GuestFs(_))));
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn download_times_out() {
+    let mut g = scratch();
+    g.fill(0, 32 * 1024 * 1024, "/file").unwrap();
+
+    let error = g
+        .with_timeout(Duration::from_millis(200), |g| {
+            g.download_to("/file", Slow(io::sink()))
+        })
+        .unwrap_err();
+    assert!(matches!(error, Error::TimedOut(_)), "{error:?}");
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn fast_calls_do_not_time_out() {
+    let mut g = scratch();
+    g.with_timeout(Duration::from_secs(60), |g| g.touch("/file"))
+        .unwrap();
+    assert!(matches!(g.cat("/missing"), Err(Error::GuestFs(_))));
+}
@@ -0,0 +1,55 @@
+// each test crate only uses some of the fixtures
+#![allow(dead_code)]
+
+use hvk::{
+    fs::GuestFileSystem,
+    guestfs::{Configuring, Device, GuestFs, Launched},
+};
+
+/// Create a handle configured by the `LIBGUESTFS_*` environment variables, like the tests of
+/// libguestfs itself, e.g. `LIBGUESTFS_BACKEND=direct`
+pub fn guestfs() -> GuestFs<'static, Configuring> {
+    GuestFs::builder().parse_environment(true).build().unwrap()
+}
+
+/// Launch an appliance with an empty, freshly formatted scratch disk mounted on `/`
+pub fn scratch() -> GuestFs<'static, Launched> {
+    let mut g = guestfs();
+    g.add_drive_scratch(64 * 1024 * 1024).unwrap();
+    let mut g = g.launch().unwrap();
+    let disk = Device::new("/dev/sda").unwrap();
+    g.mkfs("ext4", &disk).unwrap();
+    g.mount(disk, "/").unwrap();
+    g
+}
+
+/// A scratch disk with just enough of a Fedora 39 layout to be inspected, unmounted
+///
+/// Its hostname is `guest`, and its fstab mounts `/dev/sda` on `/`, and a missing device on `/srv`.
+pub fn fake_fedora() -> GuestFileSystem<'static> {
+    let mut g = scratch();
+    for dir in [
+        "/bin",
+        "/etc",
+        "/usr",
+        "/usr/bin",
+        "/var",
+        "/var/lib",
+        "/var/lib/rpm",
+    ] {
+        g.mkdir(dir).unwrap();
+    }
+    g.write(
+        "/etc/os-release",
+        b"NAME=Fedora\nID=fedora\nVERSION_ID=39\nPRETTY_NAME=\"Fedora Linux 39\"\n",
+    )
+    .unwrap();
+    g.write("/etc/hostname", b"guest\n").unwrap();
+    g.write(
+        "/etc/fstab",
+        b"/dev/sda / ext4 defaults 0 1\n/dev/sdz1 /srv ext4 defaults 0 2\n",
+    )
+    .unwrap();
+    g.umount_all().unwrap();
+    GuestFileSystem::from(g)
+}
@@ -0,0 +1,36 @@
+//! Copying files behaves like `std::fs::copy`
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::scratch;
+use hvk::fs::{GuestFileSystem, Permissions};
+use std::io;
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn copies_contents_and_permissions() {
+    let fs = GuestFileSystem::from(scratch());
+    fs.write("/from", b"contents").unwrap();
+    fs.set_permissions("/from", Permissions::from_mode(0o750))
+        .unwrap();
+    fs.write("/to", b"previous contents").unwrap();
+
+    assert_eq!(fs.copy("/from", "/to").unwrap(), 8);
+    assert_eq!(fs.read("/to").unwrap(), b"contents");
+    assert_eq!(fs.metadata("/to").unwrap().permissions().mode(), 0o750);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn directories_are_rejected() {
+    let fs = GuestFileSystem::from(scratch());
+    fs.write("/from", b"contents").unwrap();
+    fs.create_dir("/dir").unwrap();
+
+    let error = fs.copy("/from", "/dir").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::IsADirectory);
+    assert!(!fs.exists("/dir/from").unwrap());
+    let error = fs.copy("/dir", "/to").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,52 @@
+//! Device names returned by libguestfs are typed
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+use hvk::guestfs::{Device, GuestFs};
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn partitions_know_their_disk() {
+    let mut g = GuestFs::new().unwrap();
+    g.add_drive_scratch(64 * 1024 * 1024).unwrap();
+    let g = g.launch().unwrap();
+    let disk = Device::new("/dev/sda").unwrap();
+    assert_eq!(g.list_devices().unwrap().as_ref(), ["/dev/sda"]);
+
+    g.part_disk(&disk, "mbr").unwrap();
+    let partitions = g.list_partitions().unwrap();
+    assert_eq!(partitions.len(), 1);
+    let partition = &partitions[0];
+    assert_eq!(partition.disk(), disk);
+    assert_eq!(g.part_to_dev(partition).unwrap(), disk);
+    assert_eq!(g.part_to_partnum(partition).unwrap(), 1);
+    assert_eq!(g.canonical_device_name(partition).unwrap(), "/dev/sda1");
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn lvm_lists_are_empty_without_lvm() {
+    let mut g = GuestFs::new().unwrap();
+    g.add_drive_scratch(64 * 1024 * 1024).unwrap();
+    let g = g.launch().unwrap();
+    assert!(g.lvs_full().unwrap().is_empty());
+    assert!(g.vgs_full().unwrap().is_empty());
+    assert_eq!(g.pvs_full().unwrap().iter().count(), 0);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn devices_are_copied_whole() {
+    let mut g = GuestFs::new().unwrap();
+    g.add_drive_scratch(16 * 1024 * 1024).unwrap();
+    g.add_drive_scratch(16 * 1024 * 1024).unwrap();
+    let mut g = g.launch().unwrap();
+    let (src, dest) = (
+        Device::new("/dev/sda").unwrap(),
+        Device::new("/dev/sdb").unwrap(),
+    );
+
+    g.mkfs("ext4", &src).unwrap();
+    g.copy_device_to_device(&src, &dest).unwrap();
+    g.mount(dest, "/").unwrap();
+    assert!(g.exists("/lost+found").unwrap());
+}
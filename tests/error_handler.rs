@@ -0,0 +1,80 @@
+//! libguestfs errors are passed to the Rust error handler instead of being printed
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::scratch;
+use hvk::fs::{GuestFileSystem, OpenOptions};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn handler_receives_messages() {
+    let mut g = scratch();
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&messages);
+    g.set_error_handler(move |msg| sink.lock().unwrap().push(msg.to_owned()));
+
+    let error = g.cat("/missing").unwrap_err();
+    let messages = messages.lock().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(error.to_string().contains(&messages[0]));
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn silenced_errors_are_still_returned() {
+    let mut g = scratch();
+    let count = Arc::new(Mutex::new(0));
+    let sink = Arc::clone(&count);
+    g.set_error_handler(move |_| *sink.lock().unwrap() += 1);
+
+    {
+        let _quiet = g.silence_errors();
+        assert!(g.cat("/missing").is_err());
+        let _nested = g.silence_errors();
+        assert!(g.cat("/missing").is_err());
+    }
+    assert_eq!(*count.lock().unwrap(), 0);
+
+    assert!(g.cat("/missing").is_err());
+    assert_eq!(*count.lock().unwrap(), 1);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn handler_panic_is_resumed() {
+    let mut g = scratch();
+    g.set_error_handler(|_| panic!("handler panicked"));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| g.cat("/missing")));
+    assert!(result.is_err());
+
+    // the handle is still usable afterwards
+    g.clear_error_handler();
+    assert!(g.cat("/missing").is_err());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn probes_of_the_std_like_api_are_silent() {
+    let mut fs = GuestFileSystem::from(scratch());
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&messages);
+    fs.set_error_handler(move |msg| sink.lock().unwrap().push(msg.to_owned()));
+
+    fs.write("/from", b"contents").unwrap();
+    fs.copy("/from", "/to").unwrap();
+    fs.rename("/to", "/renamed").unwrap();
+    fs.symlink("/from", "/link").unwrap();
+    fs.hard_link("/from", "/hard").unwrap();
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&fs, "/new")
+        .unwrap();
+    assert!(fs.remove_dir_all("/missing").is_err());
+    assert_eq!(*messages.lock().unwrap(), Vec::<String>::new());
+}
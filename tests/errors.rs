@@ -0,0 +1,54 @@
+//! Errors of libguestfs calls carry their context and can be matched on by kind
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::scratch;
+use hvk::{
+    guestfs::{DriveFormat, DriveOptions, GuestFs},
+    Error,
+};
+use std::{ffi::OsString, io};
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn not_found() {
+    let g = scratch();
+    let Err(Error::GuestFs(error)) = g.cat("/missing") else {
+        panic!("expected a libguestfs error");
+    };
+    assert_eq!(error.api(), "cat");
+    assert_eq!(error.args(), [OsString::from("/missing")]);
+    assert_eq!(error.errno(), Some(libc::ENOENT));
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn already_exists() {
+    let g = scratch();
+    g.mkdir("/dir").unwrap();
+    let error = g.mkdir("/dir").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn into_io_error() {
+    let g = scratch();
+    let error = io::Error::from(g.rmdir("/missing").unwrap_err());
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert!(error.to_string().contains("rmdir(\"/missing\")"));
+}
+
+#[test]
+#[ignore = "needs libguestfs"]
+fn add_drive_opts_names_the_drive() {
+    let mut g = GuestFs::new().unwrap();
+    let options = DriveOptions::new("/missing.img", DriveFormat::Raw);
+    let Err(Error::GuestFs(error)) = g.add_drive_opts(&options) else {
+        panic!("expected a libguestfs error");
+    };
+    assert_eq!(error.api(), "add_drive_opts");
+    assert_eq!(error.args(), [OsString::from("/missing.img")]);
+}
@@ -0,0 +1,97 @@
+//! Events emitted by libguestfs are passed to Rust closures
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::scratch;
+use hvk::guestfs::{Event, EventMask, GuestFs};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn launch_and_close() {
+    let mut g = GuestFs::new().unwrap();
+    g.add_drive_scratch(16 * 1024 * 1024).unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let handle = g
+        .on_event(EventMask::LAUNCH_DONE | EventMask::CLOSE, move |event| {
+            sink.lock().unwrap().push(event.mask())
+        })
+        .unwrap();
+
+    let g = g.launch().unwrap();
+    assert_eq!(*events.lock().unwrap(), [EventMask::LAUNCH_DONE]);
+
+    // the handle may outlive the guestfs handle
+    drop(g);
+    assert_eq!(
+        *events.lock().unwrap(),
+        [EventMask::LAUNCH_DONE, EventMask::CLOSE]
+    );
+    drop(handle);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn enter_events_until_dropped() {
+    let g = scratch();
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&calls);
+    let handle = g
+        .on_event(EventMask::ENTER, move |event| {
+            if let Event::Enter(name) = event {
+                sink.lock().unwrap().push(name.to_vec());
+            }
+        })
+        .unwrap();
+
+    g.touch("/file").unwrap();
+    assert_eq!(*calls.lock().unwrap(), [b"touch".to_vec()]);
+
+    drop(handle);
+    g.touch("/file").unwrap();
+    assert_eq!(calls.lock().unwrap().len(), 1);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn progress() {
+    let g = scratch();
+    g.write("/file", &vec![0; 16 * 1024 * 1024]).unwrap();
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&progress);
+    let _handle = g
+        .on_event(EventMask::PROGRESS, move |event| {
+            if let &Event::Progress {
+                position, total, ..
+            } = event
+            {
+                sink.lock().unwrap().push((position, total));
+            }
+        })
+        .unwrap();
+
+    g.download("/file", "/dev/null").unwrap();
+    let progress = progress.lock().unwrap();
+    let &(position, total) = progress.last().unwrap();
+    assert_eq!(position, total);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn closure_panic_is_resumed() {
+    let g = scratch();
+    let handle = g
+        .on_event(EventMask::ENTER, |_| panic!("closure panicked"))
+        .unwrap();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| g.touch("/file")));
+    assert!(result.is_err());
+
+    // the handle is still usable afterwards
+    drop(handle);
+    g.touch("/file").unwrap();
+}
@@ -0,0 +1,117 @@
+//! Checks that every buffer, string and list returned by libguestfs is read and freed correctly
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default:
+//!
+//! ```sh
+//! cargo test --test ffi_ownership -- --ignored
+//! ```
+//!
+//! They are meant to be run under Valgrind, which catches leaks as well as frees with the
+//! wrong allocator (the appliance runs in a separate process, so it is not traced). The
+//! `leak-check` job in `.github/workflows/rust.yml` does so on every push:
+//!
+//! ```sh
+//! cargo test --no-run --test ffi_ownership
+//! valgrind --leak-check=full --errors-for-leak-kinds=definite --error-exitcode=1 \
+//!     target/debug/deps/ffi_ownership-<hash> --ignored --test-threads=1
+//! ```
+//!
+//! They can also be run by hand with AddressSanitizer on nightly:
+//!
+//! ```sh
+//! RUSTFLAGS=-Zsanitizer=address cargo +nightly test --target x86_64-unknown-linux-gnu \
+//!     --test ffi_ownership -- --ignored
+//! ```
+mod common;
+
+use common::scratch;
+use std::path::Path;
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn cat_stops_at_nul() {
+    let g = scratch();
+    g.write("/text", b"hello\0world").unwrap();
+    assert_eq!(&*g.cat("/text").unwrap(), b"hello");
+
+    g.write("/empty", b"").unwrap();
+    assert!(g.cat("/empty").unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn read_file_keeps_nul_bytes() {
+    let g = scratch();
+    g.write("/binary", b"hello\0world").unwrap();
+    assert_eq!(&*g.read_file("/binary").unwrap(), b"hello\0world");
+
+    g.write("/empty", b"").unwrap();
+    assert!(g.read_file("/empty").unwrap().is_empty());
+
+    let large: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    g.write("/large", &large).unwrap();
+    assert_eq!(&*g.read_file("/large").unwrap(), &large[..]);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn pread_returns_exact_length() {
+    let g = scratch();
+    g.write("/file", b"0123456789").unwrap();
+    assert_eq!(&*g.pread("/file", 4, 3).unwrap(), b"3456");
+    assert_eq!(&*g.pread("/file", 100, 8).unwrap(), b"89");
+    assert!(g.pread("/file", 4, 10).unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn string_lists() {
+    let g = scratch();
+    g.write("/lines", b"one\ntwo\nthree\n").unwrap();
+    assert_eq!(&*g.read_lines("/lines").unwrap(), ["one", "two", "three"]);
+
+    g.write("/empty", b"").unwrap();
+    assert!(g.read_lines("/empty").unwrap().is_empty());
+
+    // the scratch disk is not partitioned
+    assert!(g.list_partitions().unwrap().is_empty());
+    let filesystems = g.list_filesystems().unwrap();
+    assert!(filesystems
+        .iter()
+        .any(|(fs, fstype)| *fs == "/dev/sda" && fstype == "ext4"));
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn strings() {
+    let g = scratch();
+    g.mkdir("/dir").unwrap();
+    g.ln_s("dir", "/link").unwrap();
+    assert_eq!(g.readlink("/link").unwrap(), Path::new("dir"));
+    assert_eq!(g.realpath("/link").unwrap(), Path::new("/dir"));
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn errors_do_not_leak() {
+    let g = scratch();
+    assert!(g.cat("/missing").is_err());
+    assert!(g.read_file("/missing").is_err());
+    assert!(g.read_lines("/missing").is_err());
+    assert!(g.readlink("/missing").is_err());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn repeated_calls() {
+    // small leaks are easy to miss in the Valgrind report, so make them large
+    let g = scratch();
+    g.write("/file", b"line\n".repeat(100).as_slice()).unwrap();
+    for _ in 0..200 {
+        g.cat("/file").unwrap();
+        g.read_file("/file").unwrap();
+        g.read_lines("/file").unwrap();
+        g.pread("/file", 10, 0).unwrap();
+        g.realpath("/file").unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+//! Inspection finds the operating systems on the disks
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::{fake_fedora, scratch};
+use hvk::fs::{Distro, GuestFileSystem, OsType, PackageFormat};
+use std::path::Path;
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn empty_disk_has_no_os() {
+    let mut g = scratch();
+    g.umount_all().unwrap();
+    let mut fs = GuestFileSystem::from(g);
+    assert!(fs.inspect().unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn detects_linux_root() {
+    let mut fs = fake_fedora();
+    let oses = fs.inspect().unwrap();
+    assert_eq!(oses.len(), 1);
+    let os = &oses[0];
+    assert_eq!(os.root, "/dev/sda");
+    assert_eq!(os.os_type, OsType::Linux);
+    assert_eq!(os.distro, Distro::Fedora);
+    assert_eq!(os.major_version, 39);
+    assert_eq!(os.package_format, PackageFormat::Rpm);
+    assert_eq!(os.hostname.as_deref(), Some("guest"));
+    assert_eq!(
+        os.mountpoints.get(Path::new("/")).map(ToString::to_string),
+        Some("/dev/sda".to_owned())
+    );
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn inspection_mounts_nothing() {
+    let mut fs = fake_fedora();
+    let os = fs.inspect().unwrap().remove(0);
+    assert!(fs.mounts().unwrap().is_empty());
+
+    // the package database of the fake system is empty
+    assert!(fs.list_applications(&os).unwrap().is_empty());
+    assert!(fs.mounts().unwrap().is_empty());
+}
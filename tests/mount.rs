@@ -0,0 +1,58 @@
+//! Mounting the filesystems of an inspected operating system
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::{fake_fedora, scratch};
+use hvk::{
+    fs::{GuestFileSystem, MountMode, MountOptions},
+    guestfs::Device,
+};
+use std::path::Path;
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn mounts_and_unmounts_on_drop() {
+    let mut fs = fake_fedora();
+    let os = fs.inspect().unwrap().remove(0);
+
+    {
+        let mounted = fs.mount_os(&os, MountMode::ReadOnly).unwrap();
+        assert_eq!(mounted.mounted(), [Path::new("/")]);
+        assert!(mounted.exists("/etc/os-release").unwrap());
+        // the device of /srv does not exist, if inspection kept it at all
+        assert!(mounted
+            .failures()
+            .iter()
+            .all(|failure| failure.mountpoint == Path::new("/srv")));
+    }
+
+    let mounted = fs.mount_os(&os, MountMode::ReadWrite).unwrap();
+    mounted.touch("/written").unwrap();
+    mounted.unmount().unwrap();
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn mount_options_are_applied() {
+    let mut g = scratch();
+    g.umount_all().unwrap();
+    let mut fs = GuestFileSystem::from(g);
+
+    MountOptions::new()
+        .read_only(true)
+        .noexec(true)
+        .vfs_type("ext4")
+        .option("noload")
+        .mount(&mut fs, Device::new("/dev/sda").unwrap(), "/")
+        .unwrap();
+    assert_eq!(fs.mounts().unwrap().as_ref(), ["/dev/sda"]);
+    let mountpoints = fs.mountpoints().unwrap();
+    assert!(mountpoints
+        .get(Path::new("/"))
+        .is_some_and(|mountable| *mountable == "/dev/sda"));
+    assert!(fs.touch("/file").is_err());
+
+    fs.umount_all().unwrap();
+    assert!(fs.mounts().unwrap().is_empty());
+}
@@ -0,0 +1,101 @@
+//! Drives served over NBD by a local `qemu-nbd`
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default. They are
+//! skipped when `qemu-nbd` is not installed.
+use hvk::guestfs::{Device, DriveFormat, DriveOptions, DriveSource, GuestFs, Server};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A `qemu-nbd` serving a raw image on a Unix socket, killed when dropped
+struct QemuNbd {
+    child: Child,
+    socket: PathBuf,
+    image: PathBuf,
+}
+
+impl QemuNbd {
+    /// Serve an empty image, or `None` if `qemu-nbd` is not installed
+    fn serve(name: &str) -> Option<Self> {
+        let base = std::env::temp_dir().join(format!("hvk-{}-{name}", std::process::id()));
+        let image = base.with_extension("img");
+        let socket = base.with_extension("sock");
+        File::create(&image)
+            .unwrap()
+            .set_len(16 * 1024 * 1024)
+            .unwrap();
+
+        let child = Command::new("qemu-nbd")
+            .arg("--format=raw")
+            .arg("--socket")
+            .arg(&socket)
+            .arg(&image)
+            .stdout(Stdio::null())
+            .spawn();
+        let child = match child {
+            Ok(child) => child,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("qemu-nbd is not installed, skipping");
+                std::fs::remove_file(&image).unwrap();
+                return None;
+            }
+            Err(error) => panic!("failed to run qemu-nbd: {error}"),
+        };
+        let server = Self {
+            child,
+            socket,
+            image,
+        };
+        server.wait_for_socket();
+        Some(server)
+    }
+
+    fn wait_for_socket(&self) {
+        let start = Instant::now();
+        while !self.socket.exists() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "qemu-nbd did not create its socket"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn socket(&self) -> &Path {
+        &self.socket
+    }
+}
+
+impl Drop for QemuNbd {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.socket);
+        let _ = std::fs::remove_file(&self.image);
+    }
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn unix_socket_export() {
+    let Some(server) = QemuNbd::serve("export") else {
+        return;
+    };
+
+    let mut g = GuestFs::new().unwrap();
+    let source = DriveSource::nbd(Server::unix(server.socket()));
+    g.add_drive_opts(&DriveOptions::new(source, DriveFormat::Raw))
+        .unwrap();
+    let mut g = g.launch().unwrap();
+
+    let disk = Device::new("/dev/sda").unwrap();
+    assert_eq!(g.list_devices().unwrap().as_ref(), ["/dev/sda"]);
+    g.mkfs("ext4", &disk).unwrap();
+    g.mount(disk, "/").unwrap();
+    g.write("/hello", b"over nbd").unwrap();
+    assert_eq!(g.read_file("/hello").unwrap().as_bytes(), b"over nbd");
+}
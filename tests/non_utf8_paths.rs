@@ -0,0 +1,63 @@
+//! Guest file names are arbitrary bytes, check that they survive the round-trip unchanged
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::scratch;
+use hvk::fs::GuestFileSystem;
+use std::{
+    ffi::{OsStr, OsString},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+// "café" in Latin-1, which is not valid UTF-8
+const LATIN1: &[u8] = b"caf\xe9";
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn raw_listing() {
+    let g = scratch();
+    let name = OsStr::from_bytes(LATIN1);
+    g.mkdir("/dir").unwrap();
+    g.write(Path::new("/dir").join(name), b"").unwrap();
+
+    assert_eq!(g.ls("/dir").unwrap(), [OsString::from(name)]);
+    assert_eq!(g.find("/dir").unwrap(), [PathBuf::from(name)]);
+
+    let list = g.readdir("/dir").unwrap();
+    let names: Vec<&[u8]> = list
+        .iter()
+        .map(|entry| entry.name().to_bytes())
+        .filter(|name| !name.starts_with(b"."))
+        .collect();
+    assert_eq!(names, [LATIN1]);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn raw_symlink_target() {
+    let g = scratch();
+    let target = Path::new(OsStr::from_bytes(LATIN1));
+    g.ln_s(target, "/link").unwrap();
+    assert_eq!(g.readlink("/link").unwrap(), target);
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn high_level() {
+    let fs = GuestFileSystem::from(scratch());
+    let path = Path::new("/").join(OsStr::from_bytes(LATIN1));
+    fs.write(&path, "contents").unwrap();
+
+    let entries: Vec<_> = fs.read_dir("/").unwrap().map(Result::unwrap).collect();
+    let entry = entries
+        .iter()
+        .find(|entry| entry.file_name().as_bytes() == LATIN1)
+        .unwrap();
+    assert_eq!(entry.path(), path);
+    assert_eq!(entry.metadata().unwrap().len(), 8);
+
+    assert_eq!(fs.read_to_string(&path).unwrap(), "contents");
+    assert_eq!(fs.canonicalize(&path).unwrap(), path);
+}
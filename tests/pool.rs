@@ -0,0 +1,118 @@
+//! Handles can be moved between threads, and pools lend appliances out to worker threads
+//!
+//! Except for the compile-time checks, these tests need a working libguestfs appliance,
+//! so they are ignored by default.
+use hvk::{
+    fs::GuestFileSystem,
+    guestfs::{
+        CancelHandle, Configuring, DriveFormat, DriveOptions, EventHandle, EventMask, GuestFs,
+        GuestFsPool, Launched,
+    },
+};
+use std::{
+    fs::File,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+fn assert_send<T: Send>() {}
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn handles_are_send() {
+    assert_send::<GuestFs<'static, Configuring>>();
+    assert_send::<GuestFs<'static, Launched>>();
+    assert_send::<GuestFileSystem<'static>>();
+    assert_send_sync::<GuestFsPool>();
+    assert_send_sync::<EventHandle>();
+    assert_send_sync::<CancelHandle>();
+}
+
+/// An empty raw disk image in the temporary directory
+fn empty_image(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("hvk-{}-{name}.img", std::process::id()));
+    File::create(&path)
+        .unwrap()
+        .set_len(16 * 1024 * 1024)
+        .unwrap();
+    path
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn handle_moves_to_another_thread() {
+    let mut g = GuestFs::new().unwrap();
+    g.add_drive_scratch(16 * 1024 * 1024).unwrap();
+    let g = thread::spawn(move || g.launch().unwrap()).join().unwrap();
+    assert!(g.list_partitions().unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn leases_in_parallel() {
+    let image = empty_image("parallel");
+    let pool = GuestFsPool::new(2, [DriveOptions::new(&*image, DriveFormat::Raw)]).unwrap();
+    assert_eq!(pool.idle(), 2);
+
+    thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                let g = pool.lease().unwrap();
+                assert!(g.list_partitions().unwrap().is_empty());
+            });
+        }
+    });
+    assert_eq!(pool.idle(), 2);
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn discarded_appliances_are_replaced() {
+    let image = empty_image("discard");
+    let pool = GuestFsPool::new(1, [DriveOptions::new(&*image, DriveFormat::Raw)]).unwrap();
+
+    let lease = pool.try_lease().unwrap();
+    assert!(pool.try_lease().is_none());
+    lease.discard();
+    assert_eq!(pool.idle(), 0);
+
+    let g = pool.lease().unwrap();
+    assert!(g.list_partitions().unwrap().is_empty());
+    drop(g);
+    assert_eq!(pool.idle(), 1);
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn event_closures_do_not_outlive_the_lease() {
+    let image = empty_image("events");
+    let pool = GuestFsPool::new(1, [DriveOptions::new(&*image, DriveFormat::Raw)]).unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let g = pool.lease().unwrap();
+    let counter = Arc::clone(&calls);
+    let handle = g
+        .on_event(EventMask::ENTER, move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        })
+        .unwrap();
+    g.list_partitions().unwrap();
+    assert!(calls.load(Ordering::Relaxed) > 0);
+    drop(g);
+
+    // the event handle is still alive, but the closure is gone
+    let seen = calls.load(Ordering::Relaxed);
+    let g = pool.lease().unwrap();
+    g.list_partitions().unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), seen);
+    drop(handle);
+    g.list_partitions().unwrap();
+    drop(g);
+    std::fs::remove_file(image).unwrap();
+}
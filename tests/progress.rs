@@ -0,0 +1,44 @@
+//! Long-running calls report their progress to observers
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::scratch;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn download_reports_progress() {
+    let g = scratch();
+    g.fill(0, 32 * 1024 * 1024, "/file").unwrap();
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&updates);
+
+    g.with_progress(move |progress| sink.lock().unwrap().push(*progress))
+        .throttle(Duration::ZERO)
+        .download("/file", "/dev/null")
+        .unwrap();
+
+    let updates = updates.lock().unwrap();
+    assert!(updates.windows(2).all(|w| w[0].position <= w[1].position));
+    assert!(updates.last().unwrap().is_done());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn observer_only_sees_its_call() {
+    let g = scratch();
+    let updates = Arc::new(Mutex::new(0));
+    let sink = Arc::clone(&updates);
+
+    g.with_progress(move |_| *sink.lock().unwrap() += 1)
+        .fill(0, 32 * 1024 * 1024, "/file")
+        .unwrap();
+    let seen = *updates.lock().unwrap();
+
+    g.download("/file", "/dev/null").unwrap();
+    assert_eq!(*updates.lock().unwrap(), seen);
+}
@@ -0,0 +1,50 @@
+//! SBOMs list the inspected operating systems in SPDX and CycloneDX
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+#![cfg(feature = "sbom")]
+mod common;
+
+use common::fake_fedora;
+use hvk::sbom::{Sbom, System};
+
+fn inspected_sbom() -> Sbom {
+    let mut fs = fake_fedora();
+    let systems = fs
+        .inspect()
+        .unwrap()
+        .into_iter()
+        .map(|os| System {
+            applications: fs.list_applications(&os).unwrap(),
+            os,
+        })
+        .collect();
+    Sbom::new("scratch.img", systems)
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn spdx_describes_the_os() {
+    let doc = inspected_sbom().spdx();
+    assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+    assert_eq!(doc["name"], "scratch.img");
+    let os = &doc["packages"][0];
+    assert_eq!(os["primaryPackagePurpose"], "OPERATING-SYSTEM");
+    assert_eq!(os["name"], "fedora");
+    assert_eq!(os["versionInfo"], "39.0");
+    assert_eq!(doc["relationships"][0]["relationshipType"], "DESCRIBES");
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn cyclonedx_lists_the_os() {
+    let sbom = inspected_sbom();
+    let mut json = Vec::new();
+    sbom.write_cyclonedx(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains(r#""specVersion": "1.5""#));
+
+    let doc = sbom.cyclonedx();
+    assert_eq!(doc["bomFormat"], "CycloneDX");
+    assert_eq!(doc["components"][0]["type"], "operating-system");
+    assert_eq!(doc["dependencies"][0]["dependsOn"][0], "os-0");
+}
@@ -0,0 +1,122 @@
+//! The `std::fs` equivalents of `GuestFileSystem` behave like their std counterparts
+//!
+//! These tests need a working libguestfs appliance, so they are ignored by default.
+mod common;
+
+use common::{guestfs, scratch};
+use hvk::{fs::GuestFileSystem, guestfs::Device};
+use std::{io, path::Path};
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn files_larger_than_a_message() {
+    let fs = GuestFileSystem::from(scratch());
+    // well above the 4 MiB limit of a single protocol message
+    let contents: Vec<u8> = (0..16 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+    fs.write("/large", &contents).unwrap();
+    assert_eq!(fs.metadata("/large").unwrap().len(), contents.len() as u64);
+    assert_eq!(fs.read("/large").unwrap(), contents);
+
+    fs.write("/large", b"small").unwrap();
+    assert_eq!(fs.read_to_string("/large").unwrap(), "small");
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn rename_does_not_move_into_directories() {
+    let fs = GuestFileSystem::from(scratch());
+    fs.write("/file", b"contents").unwrap();
+    fs.create_dir("/dir").unwrap();
+    fs.create_dir("/full").unwrap();
+    fs.write("/full/entry", b"").unwrap();
+
+    let error = fs.rename("/file", "/dir").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::IsADirectory);
+    assert!(!fs.exists("/dir/file").unwrap());
+    let error = fs.rename("/dir", "/file").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotADirectory);
+    let error = fs.rename("/dir", "/full").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::DirectoryNotEmpty);
+    assert!(!fs.exists("/full/dir").unwrap());
+
+    // an empty directory is replaced
+    fs.rename("/full", "/dir").unwrap();
+    assert!(fs.exists("/dir/entry").unwrap());
+    assert!(!fs.exists("/full").unwrap());
+
+    // a symbolic link is replaced, not followed
+    fs.symlink("/dir", "/link").unwrap();
+    fs.rename("/file", "/link").unwrap();
+    assert_eq!(fs.read("/link").unwrap(), b"contents");
+    assert!(!fs.exists("/dir/file").unwrap());
+
+    fs.rename("/link", "/link").unwrap();
+    assert!(fs.exists("/link").unwrap());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn rename_does_not_cross_filesystems() {
+    let mut g = guestfs();
+    g.add_drive_scratch(64 * 1024 * 1024).unwrap();
+    g.add_drive_scratch(64 * 1024 * 1024).unwrap();
+    let mut g = g.launch().unwrap();
+    for disk in ["/dev/sda", "/dev/sdb"] {
+        g.mkfs("ext4", Device::new(disk).unwrap()).unwrap();
+    }
+    g.mount(Device::new("/dev/sda").unwrap(), "/").unwrap();
+    g.mkdir("/mnt").unwrap();
+    g.mount(Device::new("/dev/sdb").unwrap(), "/mnt").unwrap();
+    let fs = GuestFileSystem::from(g);
+
+    fs.write("/file", b"contents").unwrap();
+    let error = fs.rename("/file", "/mnt/file").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::CrossesDevices);
+    assert!(fs.exists("/file").unwrap());
+    assert!(!fs.exists("/mnt/file").unwrap());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn remove_dir_all_removes_links_not_their_target() {
+    let fs = GuestFileSystem::from(scratch());
+    fs.create_dir_all("/dir/sub").unwrap();
+    fs.write("/dir/sub/file", b"").unwrap();
+    fs.symlink("/dir", "/link").unwrap();
+
+    fs.remove_dir_all("/link").unwrap();
+    assert!(fs.symlink_metadata("/link").is_err());
+    assert!(fs.exists("/dir/sub/file").unwrap());
+
+    fs.write("/file", b"").unwrap();
+    let error = fs.remove_dir_all("/file").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotADirectory);
+    let error = fs.remove_dir_all("/missing").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+    fs.remove_dir_all("/dir").unwrap();
+    assert!(!fs.exists("/dir").unwrap());
+}
+
+#[test]
+#[ignore = "needs a libguestfs appliance"]
+fn links_are_not_created_inside_directories() {
+    let fs = GuestFileSystem::from(scratch());
+    fs.write("/file", b"contents").unwrap();
+    fs.create_dir("/dir").unwrap();
+    fs.symlink("/missing", "/dangling").unwrap();
+
+    for link in ["/dir", "/file", "/dangling"] {
+        let error = fs.hard_link("/file", link).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists, "{link}");
+        let error = fs.symlink("/file", link).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists, "{link}");
+    }
+    assert!(fs.read_dir("/dir").unwrap().next().is_none());
+
+    fs.hard_link("/file", "/hard").unwrap();
+    assert_eq!(fs.metadata("/file").unwrap().nlink(), 2);
+    fs.symlink("/file", "/soft").unwrap();
+    assert_eq!(fs.read_link("/soft").unwrap(), Path::new("/file"));
+}